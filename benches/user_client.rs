@@ -0,0 +1,129 @@
+// benches/user_client.rs
+//
+// User Service istemcisinin eşzamanlılık altındaki maliyeti. `register`'ın tamamı (Redis,
+// kimlik doğrulama) ölçülmez; yalnızca istemciye erişim ve istemci çağrısı ölçülür:
+// `user_client_acquire` eski `Arc<Mutex<InternalClients>>` kilitle-kopyala yolunu kilitsiz
+// havuz yoluyla karşılaştırır, `user_client_outage` devre açıkken `get_sip_credentials`'ı
+// uçtan uca (deadline + devre kesici + havuz) sürer. Her ölçümde N görev eşzamanlı çağırır.
+//
+//     cargo bench --bench user_client
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use sentiric_contracts::sentiric::user::v1::user_service_client::UserServiceClient;
+use sentiric_contracts::sentiric::user::v1::GetSipCredentialsRequest;
+use sentiric_registrar_service::config::AppConfig;
+use sentiric_registrar_service::grpc::balancer::{ChannelFactory, EndpointPool};
+use sentiric_registrar_service::grpc::client::InternalClients;
+use sentiric_registrar_service::grpc::deadline::Deadline;
+use sentiric_registrar_service::grpc::resilience::CircuitBreaker;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tonic::metadata::MetadataMap;
+use tonic::transport::{Channel, Endpoint};
+
+const CALLS_PER_TASK: u64 = 1_000;
+
+fn lazy_channel(url: &str) -> anyhow::Result<Channel> {
+    Ok(Endpoint::from_shared(url.to_string())?.connect_lazy())
+}
+
+fn bench_user_client_acquire(c: &mut Criterion) {
+    let rt = tokio::runtime::Builder::new_multi_thread().enable_all().build().expect("runtime");
+    let mut group = c.benchmark_group("user_client_acquire");
+
+    for tasks in [1u64, 16, 64, 256] {
+        group.throughput(Throughput::Elements(tasks * CALLS_PER_TASK));
+
+        // Önce: her çağrıda tokio Mutex kilitlenip istemci kopyalanır.
+        let locked = rt.block_on(async {
+            Arc::new(Mutex::new(UserServiceClient::new(lazy_channel("http://127.0.0.1:1").unwrap())))
+        });
+        group.bench_with_input(BenchmarkId::new("mutex_clone", tasks), &tasks, |b, &tasks| {
+            b.to_async(&rt).iter(|| run_tasks(tasks, locked.clone(), |holder| async move {
+                let client = holder.lock().await.clone();
+                std::hint::black_box(client);
+            }))
+        });
+
+        // Sonra: devre kesici kontrolü + havuzdan kilitsiz uç nokta seçimi.
+        let (pool, breaker) = rt.block_on(async {
+            let factory: ChannelFactory = Arc::new(lazy_channel);
+            let targets = (1..=3).map(|i| format!("http://127.0.0.{}:1", i)).collect();
+            let pool = EndpointPool::new("bench", targets, factory, 3, Duration::from_secs(30)).unwrap();
+            (Arc::new(pool), Arc::new(CircuitBreaker::new("bench", 5, Duration::from_secs(10))))
+        });
+        let lock_free = Arc::new((pool, breaker));
+        group.bench_with_input(BenchmarkId::new("lock_free_pool", tasks), &tasks, |b, &tasks| {
+            b.to_async(&rt).iter(|| run_tasks(tasks, lock_free.clone(), |holder| async move {
+                let (pool, breaker) = &*holder;
+                let permit = breaker.allow().expect("breaker closed");
+                let slot = pool.pick().expect("endpoint");
+                std::hint::black_box(UserServiceClient::new(slot.channel.clone()));
+                permit.success();
+            }))
+        });
+    }
+    group.finish();
+}
+
+/// User Service kesintisinde `register`'ın gördüğü yol: devre açık, her çağrı kesicinin
+/// kilitli (half-open) dalından geçip beklemeden UNAVAILABLE döner.
+fn bench_user_client_outage(c: &mut Criterion) {
+    let rt = tokio::runtime::Builder::new_multi_thread().enable_all().build().expect("runtime");
+    let mut group = c.benchmark_group("user_client_outage");
+
+    let config = AppConfig {
+        user_service_url: "http://127.0.0.1:1".to_string(),
+        user_service_retry_attempts: 1,
+        user_service_breaker_threshold: 1,
+        user_service_breaker_open_secs: 3600,
+        ..AppConfig::default()
+    };
+    let clients = rt.block_on(async {
+        let clients = InternalClients::connect(&config, None).expect("clients");
+        // Kapalı porta ilk çağrı devreyi açar.
+        let deadline = Deadline::from_metadata(&MetadataMap::new(), Duration::from_secs(1));
+        let _ = clients.user.get_sip_credentials(request(), &deadline, Duration::from_millis(200)).await;
+        Arc::new(clients)
+    });
+
+    for tasks in [1u64, 16, 64, 256] {
+        group.throughput(Throughput::Elements(tasks * CALLS_PER_TASK));
+        group.bench_with_input(BenchmarkId::new("get_sip_credentials", tasks), &tasks, |b, &tasks| {
+            b.to_async(&rt).iter(|| run_tasks(tasks, clients.clone(), |clients| async move {
+                let deadline = Deadline::from_metadata(&MetadataMap::new(), Duration::from_secs(1));
+                let res = clients.user.get_sip_credentials(request(), &deadline, Duration::from_millis(200)).await;
+                assert!(res.is_err());
+            }))
+        });
+    }
+    group.finish();
+}
+
+fn request() -> GetSipCredentialsRequest {
+    GetSipCredentialsRequest { sip_username: "bench".to_string(), realm: "bench.local".to_string() }
+}
+
+async fn run_tasks<H, F, Fut>(tasks: u64, holder: Arc<H>, call: F)
+where
+    H: Send + Sync + 'static,
+    F: Fn(Arc<H>) -> Fut + Copy + Send + 'static,
+    Fut: std::future::Future<Output = ()> + Send,
+{
+    let handles: Vec<_> = (0..tasks)
+        .map(|_| {
+            let holder = holder.clone();
+            tokio::spawn(async move {
+                for _ in 0..CALLS_PER_TASK {
+                    call(holder.clone()).await;
+                }
+            })
+        })
+        .collect();
+    for handle in handles {
+        handle.await.expect("task");
+    }
+}
+
+criterion_group!(benches, bench_user_client_acquire, bench_user_client_outage);
+criterion_main!(benches);
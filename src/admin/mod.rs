@@ -0,0 +1,304 @@
+// src/admin/mod.rs
+use crate::config::AppConfig;
+use crate::data::credential_cache::CredentialCache;
+use crate::data::lockout::{LockScope, LockoutStore};
+use crate::data::store::RegistrationStore;
+use crate::data::tenant_networks::{NetworkPolicy, TenantNetworks};
+use crate::metrics;
+use crate::policy::PolicyStore;
+use crate::telemetry::LogLevelController;
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Method, Request, Response, Server, StatusCode};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::convert::Infallible;
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+use tracing::{info, warn};
+
+/// Admin API'nin ihtiyaç duyduğu paylaşılan bileşenler.
+#[derive(Clone)]
+pub struct AdminState {
+    pub config: Arc<AppConfig>,
+    pub log_level: LogLevelController,
+    pub credential_cache: CredentialCache,
+    /// Tenant adlarını realm politikalarıyla karşılaştırmak için.
+    pub policies: PolicyStore,
+    /// Redis bağlantısı kurulana kadar boştur.
+    pub redis: Arc<OnceLock<AdminRedis>>,
+}
+
+/// Admin API'nin kullandığı Redis tabanlı bileşenler.
+#[derive(Clone)]
+pub struct AdminRedis {
+    pub store: RegistrationStore,
+    pub lockouts: LockoutStore,
+    pub networks: TenantNetworks,
+}
+
+/// `GET /admin/lockouts` ile dönen en fazla kilit sayısı.
+const LOCKOUT_LIST_LIMIT: usize = 1000;
+
+#[derive(Deserialize)]
+struct LogLevelRequest {
+    directives: String,
+    ttl_secs: Option<u64>,
+}
+
+/// `metrics_listen_addr` üzerinde yalnızca `/metrics` ucunu kimlik doğrulamasız sunar.
+/// Bu adres yalnızca scrape ağından erişilebilir olmalıdır.
+pub async fn serve_metrics(addr: std::net::SocketAddr) -> anyhow::Result<()> {
+    let make_svc = make_service_fn(|_conn| async {
+        Ok::<_, Infallible>(service_fn(|req: Request<Body>| async move {
+            Ok::<_, Infallible>(if req.method() == Method::GET && req.uri().path() == "/metrics" {
+                metrics_response()
+            } else {
+                json_response(StatusCode::NOT_FOUND, json!({ "error": "not found" }))
+            })
+        }))
+    });
+
+    info!(event="METRICS_SERVER_START", addr=%addr, "Metrik ucu aktif.");
+    Server::try_bind(&addr)?.serve(make_svc).await?;
+    Ok(())
+}
+
+/// `http_listen_addr` üzerinde Bearer token korumalı admin API'sini çalıştırır. Ayrı metrik
+/// adresi tanımlı değilse `/metrics` de burada, metrik veya admin token'ı ile sunulur.
+pub async fn serve(state: AdminState) -> anyhow::Result<()> {
+    let addr = state.config.http_listen_addr;
+    let make_svc = make_service_fn(move |_conn| {
+        let state = state.clone();
+        async move { Ok::<_, Infallible>(service_fn(move |req| handle(state.clone(), req))) }
+    });
+
+    info!(event="ADMIN_SERVER_START", addr=%addr, "HTTP API aktif.");
+    Server::try_bind(&addr)?.serve(make_svc).await?;
+    Ok(())
+}
+
+async fn handle(state: AdminState, req: Request<Body>) -> Result<Response<Body>, Infallible> {
+    let method = req.method().clone();
+    let path = req.uri().path().to_string();
+
+    // Metrikler tenant etiketleri ve sertifika bilgisi içerir; açık uçta sunulmaz.
+    if method == Method::GET && path == "/metrics" {
+        if state.config.metrics_listen_addr.is_some() {
+            return Ok(json_response(StatusCode::NOT_FOUND, json!({ "error": "not found" })));
+        }
+        let metrics_token = state.config.metrics_token.as_deref();
+        if !is_authorized(&state, &req) && !bearer_matches(&req, metrics_token) {
+            warn!(event="METRICS_AUTH_DENIED", "Yetkisiz metrik isteği");
+            return Ok(json_response(StatusCode::UNAUTHORIZED, json!({ "error": "unauthorized" })));
+        }
+        return Ok(metrics_response());
+    }
+
+    if !is_authorized(&state, &req) {
+        warn!(event="ADMIN_AUTH_DENIED", method=%method, path=%path, "Yetkisiz admin isteği");
+        return Ok(json_response(StatusCode::UNAUTHORIZED, json!({ "error": "unauthorized" })));
+    }
+
+    let res = match (&method, path.as_str()) {
+        (&Method::GET, "/admin/log-level") => json_response(StatusCode::OK, json!({
+            "current": state.log_level.current(),
+            "baseline": state.log_level.baseline(),
+        })),
+        (&Method::PUT, "/admin/log-level") => match read_json::<LogLevelRequest>(req).await {
+            Ok(body) => match state.log_level.apply(&body.directives, body.ttl_secs.map(Duration::from_secs)) {
+                Ok(()) => json_response(StatusCode::OK, json!({ "current": state.log_level.current() })),
+                Err(e) => json_response(StatusCode::BAD_REQUEST, json!({ "error": e.to_string() })),
+            },
+            Err(e) => json_response(StatusCode::BAD_REQUEST, json!({ "error": e })),
+        },
+        (&Method::DELETE, "/admin/log-level") => match state.log_level.reset() {
+            Ok(()) => json_response(StatusCode::OK, json!({ "current": state.log_level.current() })),
+            Err(e) => json_response(StatusCode::INTERNAL_SERVER_ERROR, json!({ "error": e.to_string() })),
+        },
+        (&Method::GET, "/admin/credential-cache") => json_response(StatusCode::OK, json!({
+            "enabled": state.credential_cache.is_enabled(),
+            "entries": state.credential_cache.size(),
+        })),
+        // ?username=..[&realm=..] ile tek kullanıcı, parametresiz tüm önbellek silinir.
+        (&Method::DELETE, "/admin/credential-cache") => {
+            let removed = match query_param(&req, "username") {
+                Some(username) => state.credential_cache.invalidate(query_param(&req, "realm").as_deref(), &username, "admin"),
+                None => state.credential_cache.clear("admin"),
+            };
+            json_response(StatusCode::OK, json!({ "removed": removed }))
+        }
+        // ?uri=sip:1001@realm ile AOR'un tüm bağlamaları (contact ve cihaz bilgisi).
+        (&Method::GET, "/admin/bindings") => match (state.redis.get(), query_param(&req, "uri")) {
+            (None, _) => redis_not_ready(),
+            (Some(_), None) => json_response(StatusCode::BAD_REQUEST, json!({ "error": "uri zorunlu" })),
+            (Some(redis), Some(uri)) => match state.policies.current().aor_from_uri(&uri, &state.config.sip_realm) {
+                None => json_response(StatusCode::BAD_REQUEST, json!({ "error": "uri'den kullanıcı adı çıkarılamadı" })),
+                Some(aor) => match redis.store.bindings(&aor).await {
+                    Ok(bindings) if bindings.is_empty() => json_response(StatusCode::NOT_FOUND, json!({ "aor": aor, "error": "binding not found" })),
+                    Ok(bindings) => json_response(StatusCode::OK, json!({ "aor": aor, "count": bindings.len(), "bindings": bindings })),
+                    Err(e) => json_response(StatusCode::INTERNAL_SERVER_ERROR, json!({ "error": e.to_string() })),
+                },
+            },
+        },
+        // ?tenant=.. ile tenant'ın etkin kayıt sayısı.
+        (&Method::GET, "/admin/usage") => match (state.redis.get(), query_param(&req, "tenant")) {
+            (None, _) => redis_not_ready(),
+            (Some(_), None) => json_response(StatusCode::BAD_REQUEST, json!({ "error": "tenant zorunlu" })),
+            (Some(redis), Some(tenant)) => match redis.store.tenant_usage(&tenant).await {
+                Ok(registrations) => json_response(StatusCode::OK, json!({ "tenant": tenant, "registrations": registrations })),
+                Err(e) => json_response(StatusCode::INTERNAL_SERVER_ERROR, json!({ "error": e.to_string() })),
+            },
+        },
+        (&Method::GET, "/admin/lockouts") => match state.redis.get() {
+            Some(redis) => match redis.lockouts.list(LOCKOUT_LIST_LIMIT).await {
+                Ok(list) => json_response(StatusCode::OK, json!({ "lockouts": list })),
+                Err(e) => json_response(StatusCode::INTERNAL_SERVER_ERROR, json!({ "error": e.to_string() })),
+            },
+            None => redis_not_ready(),
+        },
+        // ?scope=aor|source_ip&subject=.. ile tek kilit ve hata sayacı silinir.
+        (&Method::DELETE, "/admin/lockouts") => {
+            let scope = query_param(&req, "scope").and_then(|s| LockScope::parse(&s));
+            match (state.redis.get(), scope, query_param(&req, "subject")) {
+                (None, _, _) => redis_not_ready(),
+                (Some(redis), Some(scope), Some(subject)) => {
+                    let subject = subject.to_ascii_lowercase();
+                    match redis.lockouts.clear(scope, &subject).await {
+                        Ok(cleared) => {
+                            warn!(event="SECURITY_LOCKOUT_CLEARED", audit=true, scope=scope.as_str(), subject=%subject, cleared=cleared, "Kilit admin tarafından kaldırıldı");
+                            json_response(StatusCode::OK, json!({ "cleared": cleared }))
+                        }
+                        Err(e) => json_response(StatusCode::INTERNAL_SERVER_ERROR, json!({ "error": e.to_string() })),
+                    }
+                }
+                _ => json_response(StatusCode::BAD_REQUEST, json!({ "error": "scope (aor|source_ip) ve subject zorunlu" })),
+            }
+        }
+        // ?tenant=.. ile tek tenant, parametresiz tüm listeler.
+        (&Method::GET, "/admin/tenant-networks") => match (state.redis.get(), query_param(&req, "tenant")) {
+            (None, _) => redis_not_ready(),
+            (Some(redis), Some(tenant)) => match redis.networks.get(&tenant) {
+                Some(policy) => json_response(StatusCode::OK, json!({ "tenant": tenant, "networks": policy })),
+                None => json_response(StatusCode::NOT_FOUND, json!({ "error": "tenant has no network list" })),
+            },
+            (Some(redis), None) => json_response(StatusCode::OK, json!({ "tenants": redis.networks.all() })),
+        },
+        (&Method::PUT, "/admin/tenant-networks") => match (state.redis.get(), query_param(&req, "tenant")) {
+            (None, _) => redis_not_ready(),
+            (Some(_), None) => json_response(StatusCode::BAD_REQUEST, json!({ "error": "tenant zorunlu" })),
+            (Some(redis), Some(tenant)) => match read_json::<NetworkPolicy>(req).await.and_then(|body| body.compile().map_err(|e| format!("{:#}", e))) {
+                Ok(body) => match redis.networks.set(&tenant, body).await {
+                    Ok(policy) => {
+                        warn!(event="SECURITY_TENANT_NETWORKS_UPDATED", audit=true, tenant.id=%tenant, allow=?policy.allow, deny=?policy.deny, "Tenant ağ listesi güncellendi");
+                        // Liste REGISTER'da politikadan çözülen tenant ile eşleşir; yazım hatası sessizce etkisiz kalmasın.
+                        let mut body = json!({ "tenant": tenant, "networks": policy });
+                        if !state.policies.current().knows_tenant(&tenant) && !tenant.eq_ignore_ascii_case(&state.config.sip_realm) {
+                            warn!(event="TENANT_NETWORKS_UNKNOWN_TENANT", tenant.id=%tenant, "Tenant hiçbir realm, politika tenant_id'si ya da varsayılan realm ile eşleşmiyor");
+                            body["warning"] = json!(format!(
+                                "tenant '{}' matches no configured realm, policy tenant_id or the default realm; it will not apply to any REGISTER",
+                                tenant
+                            ));
+                        }
+                        json_response(StatusCode::OK, body)
+                    }
+                    Err(e) => json_response(StatusCode::INTERNAL_SERVER_ERROR, json!({ "error": e.to_string() })),
+                },
+                Err(e) => json_response(StatusCode::BAD_REQUEST, json!({ "error": e })),
+            },
+        },
+        (&Method::DELETE, "/admin/tenant-networks") => match (state.redis.get(), query_param(&req, "tenant")) {
+            (None, _) => redis_not_ready(),
+            (Some(_), None) => json_response(StatusCode::BAD_REQUEST, json!({ "error": "tenant zorunlu" })),
+            (Some(redis), Some(tenant)) => match redis.networks.remove(&tenant).await {
+                Ok(removed) => {
+                    warn!(event="SECURITY_TENANT_NETWORKS_REMOVED", audit=true, tenant.id=%tenant, removed=removed, "Tenant ağ listesi kaldırıldı");
+                    json_response(StatusCode::OK, json!({ "removed": removed }))
+                }
+                Err(e) => json_response(StatusCode::INTERNAL_SERVER_ERROR, json!({ "error": e.to_string() })),
+            },
+        },
+        _ => json_response(StatusCode::NOT_FOUND, json!({ "error": "not found" })),
+    };
+
+    info!(event="ADMIN_REQUEST", method=%method, path=%path, status=res.status().as_u16(), "Admin isteği işlendi");
+    Ok(res)
+}
+
+fn metrics_response() -> Response<Body> {
+    Response::builder()
+        .header(hyper::header::CONTENT_TYPE, "text/plain; version=0.0.4")
+        .body(Body::from(metrics::render()))
+        .unwrap_or_else(|_| Response::new(Body::empty()))
+}
+
+fn is_authorized(state: &AdminState, req: &Request<Body>) -> bool {
+    bearer_matches(req, state.config.admin_token.as_deref())
+}
+
+fn bearer_matches(req: &Request<Body>, expected: Option<&str>) -> bool {
+    let Some(expected) = expected else { return false };
+    req.headers()
+        .get(hyper::header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .map(|token| constant_time_eq(token.as_bytes(), expected.as_bytes()))
+        .unwrap_or(false)
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// Sorgu dizesinden tek bir parametreyi (percent-decode edilmiş) döner.
+fn query_param(req: &Request<Body>, name: &str) -> Option<String> {
+    req.uri().query()?
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .find(|(k, _)| *k == name)
+        .map(|(_, v)| percent_decode(v))
+        .filter(|v| !v.is_empty())
+}
+
+fn percent_decode(raw: &str) -> String {
+    let bytes = raw.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => out.push(b' '),
+            b'%' if i + 2 < bytes.len() => {
+                let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).ok().and_then(|h| u8::from_str_radix(h, 16).ok());
+                match hex {
+                    Some(b) => {
+                        out.push(b);
+                        i += 2;
+                    }
+                    None => out.push(b'%'),
+                }
+            }
+            b => out.push(b),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+async fn read_json<T: for<'de> Deserialize<'de>>(req: Request<Body>) -> Result<T, String> {
+    let bytes = hyper::body::to_bytes(req.into_body()).await.map_err(|e| e.to_string())?;
+    serde_json::from_slice(&bytes).map_err(|e| format!("Geçersiz JSON: {}", e))
+}
+
+fn redis_not_ready() -> Response<Body> {
+    json_response(StatusCode::SERVICE_UNAVAILABLE, json!({ "error": "redis bağlantısı henüz kurulmadı" }))
+}
+
+fn json_response(status: StatusCode, body: Value) -> Response<Body> {
+    Response::builder()
+        .status(status)
+        .header(hyper::header::CONTENT_TYPE, "application/json")
+        .body(Body::from(body.to_string()))
+        .unwrap_or_else(|_| Response::new(Body::empty()))
+}
@@ -1,30 +1,52 @@
 // src/app.rs
 use crate::config::AppConfig;
-use crate::grpc::service::MyRegistrarService;
+use crate::grpc::service::{MyRegistrarService, RegisterGuards};
+use crate::grpc::acl::AclLayer;
 use crate::grpc::client::InternalClients;
+use crate::data::credential_cache::CredentialCache;
+use crate::data::lockout::LockoutStore;
+use crate::data::rate_limit::RateLimiter;
+use crate::data::tenant_networks::TenantNetworks;
 use crate::data::store::{RegistrationStore, RedisConn};
-use crate::tls::load_server_tls_config;
-use crate::telemetry::SutsFormatter; 
-use std::sync::Arc;
-use tokio::sync::{mpsc, Mutex};
+use crate::tls::TlsState;
+use crate::admin::{self, AdminRedis, AdminState};
+use crate::policy::PolicyStore;
+use crate::reload::ConfigReloader;
+use arc_swap::ArcSwap;
+use crate::telemetry::{self, LogLevelController, LogRateLimiter, RedactingFields, RedactionPolicy, SpanFieldsLayer, SutsFormatter};
+use std::sync::{Arc, OnceLock};
+use tokio::net::TcpListener;
+use tokio::sync::{mpsc, Semaphore};
+use tokio_stream::wrappers::TcpListenerStream;
 use tonic::transport::Server as GrpcServer;
 use sentiric_contracts::sentiric::sip::v1::registrar_service_server::RegistrarServiceServer;
 use tracing::{info, error, warn};
-use tracing_subscriber::{fmt, prelude::*, EnvFilter, Registry};
+use tracing_subscriber::{fmt, prelude::*, reload, EnvFilter, Registry};
 
 pub struct App {
     config: Arc<AppConfig>,
+    log_limiter: LogRateLimiter,
+    log_level: LogLevelController,
 }
 
 impl App {
     pub async fn bootstrap() -> anyhow::Result<Self> {
         dotenvy::dotenv().ok();
-        let config = Arc::new(AppConfig::load_from_env()?);
+        let config = Arc::new(AppConfig::load()?);
         
         // --- SUTS v4.0 LOGGING SETUP ---
         let rust_log_env = std::env::var("RUST_LOG").unwrap_or_else(|_| config.rust_log.clone());
         let env_filter = EnvFilter::try_from_default_env().or_else(|_| EnvFilter::try_new(&rust_log_env))?;
-        let subscriber = Registry::default().with(env_filter);
+        let (env_filter, filter_handle) = reload::Layer::new(env_filter);
+        let log_level = LogLevelController::new(filter_handle, rust_log_env);
+        let redaction = RedactionPolicy::from_config(&config)?;
+        let log_limiter = LogRateLimiter::from_config(&config);
+        let tracer = telemetry::init_tracer(&config, redaction.clone())?;
+        let subscriber = Registry::default()
+            .with(env_filter)
+            .with(tracing_opentelemetry::layer().with_tracer(tracer))
+            .with(SpanFieldsLayer::new(redaction.clone()))
+            .with(log_limiter.clone());
         
         if config.log_format == "json" {
             let suts_formatter = SutsFormatter::new(
@@ -32,10 +54,11 @@ impl App {
                 config.service_version.clone(),
                 config.env.clone(),
                 config.node_hostname.clone(),
-            );
+                config.default_tenant_id.clone(),
+            ).with_redaction(redaction);
             subscriber.with(fmt::layer().event_format(suts_formatter)).init();
         } else {
-            subscriber.with(fmt::layer().compact()).init();
+            subscriber.with(fmt::layer().compact().fmt_fields(RedactingFields::new(redaction))).init();
         }
         
         info!(
@@ -43,38 +66,165 @@ impl App {
             service_name = "sentiric-registrar-service",
             version = %config.service_version,
             profile = %config.env,
+            otlp_endpoint = ?config.otlp_endpoint,
             "🚀 Registrar Service başlatılıyor (SUTS v4.0 - AutoHealing Redis)"
         );
         
-        Ok(Self { config })
+        if config.is_plaintext() {
+            eprintln!("\n!!! UYARI: GRPC_TLS_MODE=plaintext — gRPC trafiği ŞİFRESİZ ve kimlik doğrulamasız. Yalnızca geliştirme içindir. !!!\n");
+            warn!(event="INSECURE_PLAINTEXT_MODE", env=%config.env, "⚠️ gRPC TLS KAPALI (plaintext geliştirme profili). Production'da kullanmayın!");
+        }
+        
+        Ok(Self { config, log_limiter, log_level })
     }
 
     pub async fn run(self) -> anyhow::Result<()> {
         let (shutdown_tx, mut shutdown_rx) = mpsc::channel(1);
 
+        // 0. Bastırılan log satırlarının periyodik özeti
+        let log_limiter = self.log_limiter.clone();
+        let report_every = std::time::Duration::from_secs(self.config.log_suppression_report_secs.max(1));
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(report_every);
+            loop {
+                ticker.tick().await;
+                log_limiter.report_suppressed();
+            }
+        });
+
+        // 0.1 HTTP API: /admin/* yalnızca ADMIN_API_TOKEN ile; /metrics ayrı adreste ya da token ile
+        if self.config.admin_token.is_none() {
+            warn!(event="ADMIN_API_DISABLED", "ADMIN_API_TOKEN tanımlı değil, admin uçları kapalı.");
+        }
+        match self.config.metrics_listen_addr {
+            Some(addr) => {
+                tokio::spawn(async move {
+                    if let Err(e) = admin::serve_metrics(addr).await {
+                        error!(event="METRICS_SERVER_ERROR", error=%e, "Metrik ucu çöktü");
+                    }
+                });
+            }
+            None if self.config.admin_token.is_none() && self.config.metrics_token.is_none() => {
+                warn!(event="METRICS_DISABLED", "METRICS_LISTEN_ADDR, METRICS_TOKEN ve ADMIN_API_TOKEN tanımlı değil; /metrics erişilemez.");
+            }
+            None => {}
+        }
+        let credential_cache = CredentialCache::new(
+            std::time::Duration::from_secs(self.config.credential_cache_ttl_secs),
+            std::time::Duration::from_secs(self.config.credential_cache_negative_ttl_secs),
+            self.config.credential_cache_max_entries,
+        );
+        // Realm politikaları (yapılandırma + opsiyonel Redis hash, aşağıda yüklenir)
+        let policies = PolicyStore::new(self.config.policies.clone());
+        // Redis'e bağlanınca doldurulur; admin API Redis'i beklemeden açılır.
+        let admin_redis = Arc::new(OnceLock::new());
+        let admin_state = AdminState {
+            config: self.config.clone(),
+            log_level: self.log_level.clone(),
+            credential_cache: credential_cache.clone(),
+            policies: policies.clone(),
+            redis: admin_redis.clone(),
+        };
+        let admin_handle = tokio::spawn(async move {
+            if let Err(e) = admin::serve(admin_state).await {
+                error!(event="ADMIN_SERVER_ERROR", error=%e, "HTTP API çöktü");
+            }
+        });
+
         // 1. Redis Connection (Auto-Healing ConnectionManager)
         let redis_conn = self.init_redis().await?;
-        let store = RegistrationStore::new(redis_conn);
+        let store = RegistrationStore::new(redis_conn.clone());
+        store.ensure_standalone().await?;
+        let guards = RegisterGuards {
+            rate_limiter: RateLimiter::new(redis_conn.clone()),
+            lockouts: LockoutStore::new(redis_conn.clone()),
+            networks: TenantNetworks::new(redis_conn.clone(), self.config.tenant_networks_redis_key.clone()),
+            tarpit_slots: Arc::new(Semaphore::new(self.config.scanner_tarpit_max_concurrent)),
+        };
+        guards.networks.spawn_refresh(std::time::Duration::from_secs(self.config.tenant_networks_refresh_secs));
+        let _ = admin_redis.set(AdminRedis {
+            store: store.clone(),
+            lockouts: guards.lockouts.clone(),
+            networks: guards.networks.clone(),
+        });
+        if let Some(channel) = self.config.credential_cache_invalidation_channel.clone() {
+            if credential_cache.is_enabled() {
+                credential_cache.spawn_invalidation_listener(self.config.redis_url.clone(), channel);
+            }
+        }
+
+        // 1.1 Realm politikalarının Redis katmanı
+        if let Some(hash_key) = self.config.policy_redis_key.clone() {
+            let policies = policies.clone();
+            let refresh_every = std::time::Duration::from_secs(self.config.policy_refresh_secs);
+            tokio::spawn(async move {
+                let mut ticker = tokio::time::interval(refresh_every);
+                loop {
+                    ticker.tick().await;
+                    if let Err(e) = policies.reload_from_redis(redis_conn.clone(), &hash_key).await {
+                        warn!(event="POLICY_RELOAD_FAIL", error=%e, "Realm politikaları Redis'ten yüklenemedi, mevcut politikalar korunuyor");
+                    }
+                }
+            });
+        }
+
+        // 2. TLS materyali (dosya değişikliklerinde otomatik yenilenir); plaintext profilde yok.
+        let tls = if self.config.is_plaintext() {
+            None
+        } else {
+            let tls = TlsState::load(self.config.clone()).await?;
+            tls.spawn_watcher()?;
+            Some(tls)
+        };
 
-        // 2. Internal gRPC Clients
-        let clients = Arc::new(Mutex::new(InternalClients::connect(&self.config).await?));
+        // 3. Internal gRPC Clients
+        // Kanallar havuz içinde atomik olarak değiştirilir; sıcak yolda kilit yoktur.
+        let clients = Arc::new(InternalClients::connect(&self.config, tls.as_ref())?);
+        if let Some(tls) = tls.clone() {
+            // Sertifika rotasyonunda kanal yeni kimlikle yeniden kurulur; süren çağrılar eski kanalı kullanmaya devam eder.
+            let clients = clients.clone();
+            let mut rotations = tls.subscribe();
+            tokio::spawn(async move {
+                while rotations.changed().await.is_ok() {
+                    match clients.reconnect(Some(&tls)) {
+                        Ok(()) => {
+                            info!(event="USER_CLIENT_ROTATED", "User Service kanalı yeni sertifikayla yeniden kuruldu");
+                        }
+                        Err(e) => error!(event="USER_CLIENT_ROTATE_FAIL", error=%e, "User Service kanalı yenilenemedi, eski kanal kullanılıyor"),
+                    }
+                }
+            });
+        }
 
-        // 3. gRPC Server
-        let tls_config = load_server_tls_config(&self.config).await?;
-        let grpc_service = MyRegistrarService::new(store, clients, self.config.clone());
+        // 4. gRPC Server
+        let shared_config = Arc::new(ArcSwap::new(self.config.clone()));
+        ConfigReloader::new(shared_config.clone(), policies.clone(), self.log_limiter.clone(), self.log_level.clone()).spawn()?;
+        if self.config.env == "production" && self.config.contact_blocked_networks.is_empty() {
+            warn!(event="CONTACT_BLOCKED_NETWORKS_EMPTY", "contact_blocked_networks boş; platformun iç ağları Contact hedefi olarak kabul ediliyor. Tanımlayıp CONTACT_BLOCKED_NETWORKS_REQUIRED=true yapın.");
+        }
+        if tls.is_some() && self.config.grpc_acl.is_empty() {
+            warn!(event="GRPC_ACL_DISABLED", "grpc_acl tanımlı değil; CA'nın imzaladığı her istemci tüm RPC'leri çağırabilir.");
+        }
+        let acl = AclLayer::new(shared_config.clone());
+        let grpc_service = MyRegistrarService::new(store, clients, credential_cache, guards, shared_config.clone(), policies);
 
-        info!(event="GRPC_SERVER_START", addr=%self.config.grpc_listen_addr, "Registrar gRPC aktif.");
+        info!(event="GRPC_SERVER_START", addr=%self.config.grpc_listen_addr, tls_mode=%self.config.grpc_tls_mode, "Registrar gRPC aktif.");
 
-        let server_handle = tokio::spawn(async move {
-            GrpcServer::builder()
-                .tls_config(tls_config).unwrap()
-                .add_service(RegistrarServiceServer::new(grpc_service))
-                .serve_with_shutdown(self.config.grpc_listen_addr, async {
-                    shutdown_rx.recv().await;
-                    info!(event="GRPC_SHUTDOWN", "gRPC sunucusu kapanıyor...");
-                })
-                .await
-        });
+        let router = GrpcServer::builder().layer(acl).add_service(RegistrarServiceServer::new(grpc_service));
+        let shutdown = async move {
+            shutdown_rx.recv().await;
+            info!(event="GRPC_SHUTDOWN", "gRPC sunucusu kapanıyor...");
+        };
+        let server_handle = match &tls {
+            Some(tls) => {
+                let incoming = tls.incoming(self.config.grpc_listen_addr, shared_config.clone()).await?;
+                tokio::spawn(router.serve_with_incoming_shutdown(incoming, shutdown))
+            }
+            None => {
+                let listener = TcpListener::bind(self.config.grpc_listen_addr).await?;
+                tokio::spawn(router.serve_with_incoming_shutdown(TcpListenerStream::new(listener), shutdown))
+            }
+        };
 
         tokio::select! {
             res = server_handle => { 
@@ -86,6 +236,8 @@ impl App {
         }
 
         let _ = shutdown_tx.send(());
+        admin_handle.abort();
+        telemetry::shutdown_tracer();
         Ok(())
     }
 
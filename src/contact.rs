@@ -0,0 +1,273 @@
+// src/contact.rs
+use crate::data::tenant_networks::Cidr;
+use std::net::IpAddr;
+
+/// Contact URI'nin reddedilme nedeni; metrik etiketi ve `x-registrar-contact-reason` değeri.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContactRejection {
+    Empty,
+    TooLong,
+    InvalidCharacters,
+    Malformed,
+    SchemeNotAllowed,
+    TransportNotAllowed,
+    InvalidHost,
+    InvalidPort,
+    Loopback,
+    LinkLocal,
+    Unspecified,
+    Multicast,
+    BlockedNetwork,
+}
+
+impl ContactRejection {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Empty => "empty",
+            Self::TooLong => "too_long",
+            Self::InvalidCharacters => "invalid_characters",
+            Self::Malformed => "malformed",
+            Self::SchemeNotAllowed => "scheme_not_allowed",
+            Self::TransportNotAllowed => "transport_not_allowed",
+            Self::InvalidHost => "invalid_host",
+            Self::InvalidPort => "invalid_port",
+            Self::Loopback => "loopback",
+            Self::LinkLocal => "link_local",
+            Self::Unspecified => "unspecified",
+            Self::Multicast => "multicast",
+            Self::BlockedNetwork => "blocked_network",
+        }
+    }
+}
+
+impl std::fmt::Display for ContactRejection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Contact doğrulama kuralları (yapılandırmadan).
+#[derive(Debug, Clone, Copy)]
+pub struct ContactRules<'a> {
+    pub max_length: usize,
+    pub allowed_schemes: &'a [String],
+    pub allowed_transports: &'a [String],
+    /// Kayıt hedefi olamayacak iç ağlar (CIDR).
+    pub blocked_networks: &'a [String],
+}
+
+/// Contact'ın ayrıştırılmış URI kısmı.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContactUri {
+    pub scheme: String,
+    pub user: Option<String>,
+    pub host: String,
+    pub port: Option<u16>,
+    pub transport: String,
+}
+
+/// Contact'ı doğrular ve saklanacak biçimini (baştaki/sondaki boşluklar atılmış) döner.
+pub fn validate(raw: &str, rules: &ContactRules<'_>) -> Result<String, ContactRejection> {
+    let contact = raw.trim();
+    if contact.is_empty() {
+        return Err(ContactRejection::Empty);
+    }
+    if contact.len() > rules.max_length {
+        return Err(ContactRejection::TooLong);
+    }
+    // Boşluk ve kontrol karakterleri Redis'e ve oradan B2BUA yönlendirmesine taşınmaz.
+    if contact.chars().any(|c| c.is_control() || c.is_whitespace()) {
+        return Err(ContactRejection::InvalidCharacters);
+    }
+
+    let uri = parse(contact)?;
+    if !rules.allowed_schemes.is_empty() && !rules.allowed_schemes.iter().any(|s| s.eq_ignore_ascii_case(&uri.scheme)) {
+        return Err(ContactRejection::SchemeNotAllowed);
+    }
+    if !rules.allowed_transports.is_empty() && !rules.allowed_transports.iter().any(|t| t.eq_ignore_ascii_case(&uri.transport)) {
+        return Err(ContactRejection::TransportNotAllowed);
+    }
+    check_host(&uri.host, rules.blocked_networks)?;
+    Ok(contact.to_string())
+}
+
+/// `[<]scheme:[user@]host[:port][;params][>]` biçimini ayrıştırır. Köşeli parantez
+/// dışındaki başlık parametreleri (ör. `+sip.instance`) yok sayılır.
+pub fn parse(contact: &str) -> Result<ContactUri, ContactRejection> {
+    let uri = match contact.strip_prefix('<') {
+        Some(rest) => rest.split_once('>').map(|(uri, _)| uri).ok_or(ContactRejection::Malformed)?,
+        None => contact,
+    };
+    let (scheme, rest) = uri.split_once(':').ok_or(ContactRejection::Malformed)?;
+    if scheme.is_empty() || !scheme.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.')) {
+        return Err(ContactRejection::Malformed);
+    }
+    let address = rest.split([';', '?']).next().unwrap_or_default();
+    let (user, hostport) = match address.rsplit_once('@') {
+        Some((user, hostport)) if !user.is_empty() => (Some(user.to_string()), hostport),
+        Some(_) => return Err(ContactRejection::Malformed),
+        None => (None, address),
+    };
+
+    let (host, port) = if let Some(v6) = hostport.strip_prefix('[') {
+        let (host, after) = v6.split_once(']').ok_or(ContactRejection::InvalidHost)?;
+        match after {
+            "" => (host, None),
+            _ => (host, Some(after.strip_prefix(':').ok_or(ContactRejection::InvalidPort)?)),
+        }
+    } else {
+        match hostport.split_once(':') {
+            Some((host, port)) => (host, Some(port)),
+            None => (hostport, None),
+        }
+    };
+    if host.is_empty() {
+        return Err(ContactRejection::InvalidHost);
+    }
+    let port = match port {
+        Some(p) => Some(p.parse::<u16>().ok().filter(|p| *p > 0).ok_or(ContactRejection::InvalidPort)?),
+        None => None,
+    };
+
+    Ok(ContactUri {
+        scheme: scheme.to_ascii_lowercase(),
+        user,
+        host: host.to_ascii_lowercase(),
+        port,
+        transport: crate::policy::transport_from_contact(uri),
+    })
+}
+
+/// Hedef adresin kayıt için güvenli olduğunu doğrular. Alan adları yalnızca biçim
+/// olarak denetlenir; çözümleme yapılmaz.
+fn check_host(host: &str, blocked_networks: &[String]) -> Result<(), ContactRejection> {
+    let Ok(ip) = host.parse::<IpAddr>().map(|ip| ip.to_canonical()) else {
+        if host == "localhost" || host.ends_with(".localhost") {
+            return Err(ContactRejection::Loopback);
+        }
+        let valid = host.len() <= 253 && host.split('.').all(|label| {
+            !label.is_empty() && label.len() <= 63
+                && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+                && !label.starts_with('-') && !label.ends_with('-')
+        });
+        return if valid { Ok(()) } else { Err(ContactRejection::InvalidHost) };
+    };
+
+    if ip.is_loopback() {
+        return Err(ContactRejection::Loopback);
+    }
+    if ip.is_unspecified() {
+        return Err(ContactRejection::Unspecified);
+    }
+    if ip.is_multicast() || matches!(ip, IpAddr::V4(v4) if v4.is_broadcast()) {
+        return Err(ContactRejection::Multicast);
+    }
+    let link_local = match ip {
+        IpAddr::V4(v4) => v4.is_link_local(),
+        IpAddr::V6(v6) => v6.segments()[0] & 0xffc0 == 0xfe80,
+    };
+    if link_local {
+        return Err(ContactRejection::LinkLocal);
+    }
+    // Yapılandırma doğrulamasında geçersiz CIDR'lar reddedildiği için burada atlanabilir.
+    if blocked_networks.iter().filter_map(|c| c.parse::<Cidr>().ok()).any(|c| c.contains(ip)) {
+        return Err(ContactRejection::BlockedNetwork);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(items: &[&str]) -> Vec<String> {
+        items.iter().map(|s| s.to_string()).collect()
+    }
+
+    fn check(raw: &str) -> Result<String, ContactRejection> {
+        let schemes = strings(&["sip", "sips"]);
+        let transports = strings(&["udp", "tcp", "tls", "ws", "wss"]);
+        let blocked = strings(&["10.88.0.0/16", "fd00:88::/32"]);
+        validate(raw, &ContactRules { max_length: 256, allowed_schemes: &schemes, allowed_transports: &transports, blocked_networks: &blocked })
+    }
+
+    #[test]
+    fn parses_ipv6_brackets_credentials_and_header_params() {
+        let uri = parse("sip:alice@[2001:db8::1]:5070").unwrap();
+        assert_eq!((uri.host.as_str(), uri.port), ("2001:db8::1", Some(5070)));
+        assert_eq!(parse("sip:alice@[2001:db8::1]").unwrap().port, None);
+
+        let uri = parse("sip:alice:secret@Phone.Example.com:5061").unwrap();
+        assert_eq!(uri.user.as_deref(), Some("alice:secret"));
+        assert_eq!((uri.host.as_str(), uri.port), ("phone.example.com", Some(5061)));
+
+        let uri = parse(r#"<sips:alice@203.0.113.7:5061;transport=tls>;expires=3600;+sip.instance="<urn:uuid:1>""#).unwrap();
+        assert_eq!(uri.scheme, "sips");
+        assert_eq!(uri.host, "203.0.113.7");
+        assert_eq!(uri.transport, "tls");
+
+        let uri = parse("sip:203.0.113.7;transport=TCP").unwrap();
+        assert_eq!((uri.user, uri.transport.as_str()), (None, "tcp"));
+    }
+
+    #[test]
+    fn valid_contacts_are_trimmed_and_kept() {
+        assert_eq!(check("  <sip:alice@203.0.113.7:5060>;expires=60 "), Ok("<sip:alice@203.0.113.7:5060>;expires=60".to_string()));
+        assert!(check("sip:alice@[2001:db8::1]:5060;transport=tcp").is_ok());
+        // Özel ağlar (engellenmemişse) meşru hedeftir.
+        assert!(check("sip:alice@192.168.1.20:5060").is_ok());
+    }
+
+    #[test]
+    fn mapped_ipv4_is_checked_as_ipv4() {
+        assert_eq!(check("sip:alice@[::ffff:127.0.0.1]:5060"), Err(ContactRejection::Loopback));
+        assert_eq!(check("sip:alice@[::ffff:10.88.1.2]"), Err(ContactRejection::BlockedNetwork));
+        assert_eq!(check("sip:alice@[::ffff:169.254.0.1]"), Err(ContactRejection::LinkLocal));
+    }
+
+    #[test]
+    fn port_bounds() {
+        assert_eq!(check("sip:alice@203.0.113.7:0"), Err(ContactRejection::InvalidPort));
+        assert_eq!(check("sip:alice@203.0.113.7:65536"), Err(ContactRejection::InvalidPort));
+        assert_eq!(check("sip:alice@[2001:db8::1]:0"), Err(ContactRejection::InvalidPort));
+        assert_eq!(check("sip:alice@[2001:db8::1]5060"), Err(ContactRejection::InvalidPort));
+        assert!(check("sip:alice@203.0.113.7:1").is_ok());
+        assert!(check("sip:alice@203.0.113.7:65535").is_ok());
+    }
+
+    #[test]
+    fn every_rejection_reason() {
+        let long = format!("sip:{}@203.0.113.7", "a".repeat(300));
+        let cases = [
+            ("   ", ContactRejection::Empty),
+            (long.as_str(), ContactRejection::TooLong),
+            ("sip:ali ce@203.0.113.7", ContactRejection::InvalidCharacters),
+            ("sip:alice@203.0.113.7\r\nX-Evil: 1", ContactRejection::InvalidCharacters),
+            ("alice-at-nowhere", ContactRejection::Malformed),
+            ("<sip:alice@203.0.113.7", ContactRejection::Malformed),
+            ("sip:@203.0.113.7", ContactRejection::Malformed),
+            ("http://203.0.113.7/", ContactRejection::SchemeNotAllowed),
+            ("tel:+15551234567", ContactRejection::SchemeNotAllowed),
+            ("sip:alice@203.0.113.7;transport=sctp", ContactRejection::TransportNotAllowed),
+            ("sip:alice@-bad-.example", ContactRejection::InvalidHost),
+            ("sip:alice@[2001:db8::1", ContactRejection::InvalidHost),
+            ("sip:alice@:5060", ContactRejection::InvalidHost),
+            ("sip:alice@203.0.113.7:abc", ContactRejection::InvalidPort),
+            ("sip:alice@127.0.0.1", ContactRejection::Loopback),
+            ("sip:alice@[::1]", ContactRejection::Loopback),
+            ("sip:alice@LocalHost", ContactRejection::Loopback),
+            ("sip:alice@169.254.10.1", ContactRejection::LinkLocal),
+            ("sip:alice@[fe80::1]", ContactRejection::LinkLocal),
+            ("sip:alice@0.0.0.0", ContactRejection::Unspecified),
+            ("sip:alice@[::]", ContactRejection::Unspecified),
+            ("sip:alice@224.0.0.1", ContactRejection::Multicast),
+            ("sip:alice@255.255.255.255", ContactRejection::Multicast),
+            ("sip:alice@[ff02::1]", ContactRejection::Multicast),
+            ("sip:alice@10.88.4.5", ContactRejection::BlockedNetwork),
+            ("sip:alice@[fd00:88::5]", ContactRejection::BlockedNetwork),
+        ];
+        for (raw, expected) in cases {
+            assert_eq!(check(raw), Err(expected), "{:?}", raw);
+        }
+    }
+}
@@ -0,0 +1,319 @@
+// src/data/credential_cache.rs
+use crate::metrics;
+use sentiric_contracts::sentiric::user::v1::GetSipCredentialsResponse;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, VecDeque};
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+use tokio_stream::StreamExt;
+use tonic::Code;
+use tracing::{debug, info, warn};
+
+type CacheKey = (String, String); // (realm, username)
+
+/// Önbellekte tutulan sonuç: bulunan kimlik bilgisi veya kalıcı ret (negatif kayıt).
+pub type CachedCredential = Result<GetSipCredentialsResponse, (Code, String)>;
+
+struct Entry {
+    value: CachedCredential,
+    expires_at: Instant,
+    seq: u64,
+}
+
+#[derive(Default)]
+struct Shard {
+    entries: HashMap<CacheKey, Entry>,
+    // Ekleme sırası; kapasite aşılınca en eski kayıt atılır. `seq` uyuşmayanlar bayattır.
+    order: VecDeque<(CacheKey, u64)>,
+    next_seq: u64,
+}
+
+/// En fazla parça sayısı; her parça kendi kilidini taşır, farklı kullanıcılar birbirini beklemez.
+const MAX_SHARDS: usize = 16;
+
+/// User Service yanıtları için (realm, kullanıcı) anahtarlı, süreli ve boyutu sınırlı
+/// süreç içi önbellek. Geçici (altyapı) hatalar önbelleğe alınmaz.
+/// Kayıtlar kullanıcı adına göre parçalara dağıtılır; kapasite parçalara eşit bölünür.
+#[derive(Clone)]
+pub struct CredentialCache {
+    ttl: Duration,
+    negative_ttl: Duration,
+    max_entries: usize,
+    shard_capacity: usize,
+    shards: Arc<[Mutex<Shard>]>,
+    size: Arc<AtomicUsize>,
+}
+
+impl CredentialCache {
+    pub fn new(ttl: Duration, negative_ttl: Duration, max_entries: usize) -> Self {
+        let shard_count = max_entries.clamp(1, MAX_SHARDS);
+        Self {
+            ttl,
+            negative_ttl,
+            max_entries,
+            shard_capacity: max_entries / shard_count,
+            shards: (0..shard_count).map(|_| Mutex::new(Shard::default())).collect(),
+            size: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        !self.ttl.is_zero() && self.max_entries > 0
+    }
+
+    pub fn get(&self, realm: &str, username: &str) -> Option<CachedCredential> {
+        if !self.is_enabled() {
+            return None;
+        }
+        let key = cache_key(realm, username);
+        let mut shard = self.shard(username);
+        let (hit, expired) = match shard.entries.get(&key) {
+            Some(entry) if entry.expires_at > Instant::now() => (Some(entry.value.clone()), false),
+            Some(_) => (None, true),
+            None => (None, false),
+        };
+        if expired {
+            let before = shard.entries.len();
+            shard.entries.remove(&key);
+            self.adjust_size(before, shard.entries.len());
+        }
+        drop(shard);
+        let result = match &hit {
+            Some(Ok(_)) => "hit",
+            Some(Err(_)) => "negative_hit",
+            None => "miss",
+        };
+        metrics::inc_counter("registrar_credential_cache_requests_total", &[("result", result)]);
+        hit
+    }
+
+    pub fn insert(&self, realm: &str, username: &str, value: CachedCredential) {
+        if !self.is_enabled() {
+            return;
+        }
+        let ttl = if value.is_ok() { self.ttl } else { self.negative_ttl };
+        if ttl.is_zero() {
+            return;
+        }
+        let key = cache_key(realm, username);
+        let mut shard = self.shard(username);
+        let before = shard.entries.len();
+        let seq = shard.next_seq;
+        shard.next_seq += 1;
+        shard.order.push_back((key.clone(), seq));
+        shard.entries.insert(key, Entry { value, expires_at: Instant::now() + ttl, seq });
+
+        // Aynı anahtarın tekrar eklenmesiyle biriken bayat sıra kayıtları da burada temizlenir.
+        while shard.entries.len() > self.shard_capacity || shard.order.len() > self.shard_capacity * 2 {
+            let Some((old_key, old_seq)) = shard.order.pop_front() else { break };
+            if shard.entries.get(&old_key).is_some_and(|e| e.seq == old_seq) {
+                shard.entries.remove(&old_key);
+            }
+        }
+        self.adjust_size(before, shard.entries.len());
+    }
+
+    /// Tek bir kullanıcıyı siler; `realm` yoksa kullanıcının tüm realm'lerdeki kayıtları silinir.
+    pub fn invalidate(&self, realm: Option<&str>, username: &str, source: &str) -> usize {
+        let mut shard = self.shard(username);
+        let before = shard.entries.len();
+        match realm {
+            Some(realm) => {
+                shard.entries.remove(&cache_key(realm, username));
+            }
+            None => shard.entries.retain(|(_, user), _| user != username),
+        }
+        let after = shard.entries.len();
+        self.adjust_size(before, after);
+        drop(shard);
+        metrics::inc_counter("registrar_credential_cache_invalidations_total", &[("source", source)]);
+        let removed = before - after;
+        debug!(event="CREDENTIAL_CACHE_INVALIDATE", realm=?realm, sip.user=%username, removed=removed, source=%source, "Kimlik bilgisi önbellekten silindi");
+        removed
+    }
+
+    pub fn clear(&self, source: &str) -> usize {
+        let mut removed = 0;
+        for shard in self.shards.iter() {
+            let mut shard = lock(shard);
+            self.adjust_size(shard.entries.len(), 0);
+            removed += shard.entries.len();
+            shard.entries.clear();
+            shard.order.clear();
+        }
+        metrics::inc_counter("registrar_credential_cache_invalidations_total", &[("source", source)]);
+        info!(event="CREDENTIAL_CACHE_CLEARED", removed=removed, source=%source, "Kimlik bilgisi önbelleği temizlendi");
+        removed
+    }
+
+    pub fn size(&self) -> usize {
+        self.size.load(Ordering::Relaxed)
+    }
+
+    /// Redis pub/sub kanalını dinler. Mesaj biçimi: `kullanıcı@realm`, `kullanıcı`
+    /// (tüm realm'ler) veya `*` (tümü). Bağlantı koparsa yeniden bağlanır ve kaçırılmış
+    /// olabilecek mesajlar nedeniyle önbelleği boşaltır.
+    pub fn spawn_invalidation_listener(&self, redis_url: String, channel: String) {
+        let cache = self.clone();
+        tokio::spawn(async move {
+            loop {
+                if let Err(e) = cache.listen(&redis_url, &channel).await {
+                    warn!(event="CREDENTIAL_CACHE_PUBSUB_FAIL", error=%e, "Önbellek geçersizleştirme kanalı koptu, 5sn sonra tekrar");
+                }
+                tokio::time::sleep(Duration::from_secs(5)).await;
+            }
+        });
+    }
+
+    async fn listen(&self, redis_url: &str, channel: &str) -> anyhow::Result<()> {
+        let mut pubsub = redis::Client::open(redis_url)?.get_async_pubsub().await?;
+        pubsub.subscribe(channel).await?;
+        info!(event="CREDENTIAL_CACHE_PUBSUB_START", channel=%channel, "Kimlik bilgisi geçersizleştirme kanalı dinleniyor");
+        self.clear("pubsub_resubscribe");
+
+        let mut messages = pubsub.on_message();
+        while let Some(msg) = messages.next().await {
+            let payload: String = match msg.get_payload() {
+                Ok(p) => p,
+                Err(e) => {
+                    warn!(event="CREDENTIAL_CACHE_PUBSUB_BAD_MESSAGE", error=%e, "Geçersiz geçersizleştirme mesajı");
+                    continue;
+                }
+            };
+            match Invalidation::parse(&payload) {
+                Invalidation::All => {
+                    self.clear("pubsub");
+                }
+                Invalidation::User { realm, username } => {
+                    self.invalidate(realm, username, "pubsub");
+                }
+            }
+        }
+        anyhow::bail!("pub/sub akışı kapandı")
+    }
+
+    /// Kullanıcının parçası; aynı kullanıcının tüm realm kayıtları aynı parçadadır.
+    fn shard(&self, username: &str) -> MutexGuard<'_, Shard> {
+        let mut hasher = DefaultHasher::new();
+        username.hash(&mut hasher);
+        lock(&self.shards[hasher.finish() as usize % self.shards.len()])
+    }
+
+    /// Toplam kayıt sayısını günceller; parça kilidi tutulurken çağrılır ki sayaç eksiye düşmesin.
+    fn adjust_size(&self, before: usize, after: usize) {
+        let size = if after >= before {
+            self.size.fetch_add(after - before, Ordering::Relaxed) + (after - before)
+        } else {
+            self.size.fetch_sub(before - after, Ordering::Relaxed) - (before - after)
+        };
+        metrics::set_gauge("registrar_credential_cache_entries", &[], size as f64);
+    }
+}
+
+fn lock(shard: &Mutex<Shard>) -> MutexGuard<'_, Shard> {
+    shard.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Geçersizleştirme kanalındaki bir mesajın anlamı.
+#[derive(Debug, PartialEq, Eq)]
+enum Invalidation<'a> {
+    All,
+    User { realm: Option<&'a str>, username: &'a str },
+}
+
+impl<'a> Invalidation<'a> {
+    /// `*` -> tümü, `kullanıcı@realm` -> tek realm, `kullanıcı` -> tüm realm'ler.
+    fn parse(payload: &'a str) -> Self {
+        match payload.trim() {
+            "*" => Self::All,
+            target => match target.split_once('@') {
+                Some((username, realm)) => Self::User { realm: Some(realm), username },
+                None => Self::User { realm: None, username: target },
+            },
+        }
+    }
+}
+
+fn cache_key(realm: &str, username: &str) -> CacheKey {
+    (realm.to_ascii_lowercase(), username.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn found() -> CachedCredential {
+        Ok(GetSipCredentialsResponse::default())
+    }
+
+    fn not_found() -> CachedCredential {
+        Err((Code::NotFound, "yok".to_string()))
+    }
+
+    #[test]
+    fn entries_expire_after_ttl() {
+        let cache = CredentialCache::new(Duration::from_millis(30), Duration::from_millis(30), 16);
+        cache.insert("Demo", "1001", found());
+        // Realm büyük/küçük harf duyarsızdır.
+        assert!(matches!(cache.get("demo", "1001"), Some(Ok(_))));
+
+        std::thread::sleep(Duration::from_millis(50));
+        assert!(cache.get("demo", "1001").is_none());
+        assert_eq!(cache.size(), 0);
+    }
+
+    #[test]
+    fn negative_entries_use_their_own_ttl() {
+        let cache = CredentialCache::new(Duration::from_secs(60), Duration::from_millis(30), 16);
+        cache.insert("demo", "ghost", not_found());
+        assert!(matches!(cache.get("demo", "ghost"), Some(Err((Code::NotFound, _)))));
+        std::thread::sleep(Duration::from_millis(50));
+        assert!(cache.get("demo", "ghost").is_none());
+
+        // negative_ttl 0 ise ret önbelleğe alınmaz.
+        let cache = CredentialCache::new(Duration::from_secs(60), Duration::ZERO, 16);
+        cache.insert("demo", "ghost", not_found());
+        assert!(cache.get("demo", "ghost").is_none());
+    }
+
+    #[test]
+    fn capacity_evicts_oldest_entries() {
+        let cache = CredentialCache::new(Duration::from_secs(60), Duration::from_secs(60), 1);
+        cache.insert("demo", "1001", found());
+        cache.insert("demo", "1002", found());
+        assert!(cache.get("demo", "1001").is_none());
+        assert!(cache.get("demo", "1002").is_some());
+        assert_eq!(cache.size(), 1);
+
+        let cache = CredentialCache::new(Duration::from_secs(60), Duration::from_secs(60), 64);
+        for i in 0..1000 {
+            cache.insert("demo", &i.to_string(), found());
+            cache.insert("demo", &i.to_string(), found());
+        }
+        assert!(cache.size() <= 64);
+        assert!(cache.get("demo", "999").is_some());
+    }
+
+    #[test]
+    fn invalidation_targets_realm_user_or_everything() {
+        let cache = CredentialCache::new(Duration::from_secs(60), Duration::from_secs(60), 64);
+        for realm in ["a", "b"] {
+            cache.insert(realm, "1001", found());
+            cache.insert(realm, "1002", found());
+        }
+        assert_eq!(cache.invalidate(Some("A"), "1001", "test"), 1);
+        assert_eq!(cache.invalidate(None, "1002", "test"), 2);
+        assert!(cache.get("b", "1001").is_some());
+        assert_eq!(cache.clear("test"), 1);
+        assert_eq!(cache.size(), 0);
+    }
+
+    #[test]
+    fn pubsub_messages_are_parsed() {
+        assert_eq!(Invalidation::parse(" * "), Invalidation::All);
+        assert_eq!(Invalidation::parse("1001@demo\n"), Invalidation::User { realm: Some("demo"), username: "1001" });
+        assert_eq!(Invalidation::parse("1001"), Invalidation::User { realm: None, username: "1001" });
+    }
+}
@@ -0,0 +1,204 @@
+// src/data/lockout.rs
+use crate::data::store::RedisConn;
+use crate::grpc::resilience::random_u64;
+use redis::{AsyncCommands, Script};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+const FAILURES_PREFIX: &str = "bf:fail";
+const LOCK_PREFIX: &str = "bf:lock";
+
+/// Başarısız denemeyi kayan pencereye (zaman damgalı sorted set) ekler; eşiğe
+/// ulaşılırsa kilit anahtarını yazar ve sayacı sıfırlar.
+/// KEYS: sayaç, kilit. ARGV: pencere ms, eşik, kilit süresi sn, benzersiz üye.
+/// Dönüş: {penceredeki hata sayısı, kilitlendi mi (1/0)}.
+const RECORD_FAILURE_SCRIPT: &str = r#"
+local t = redis.call('TIME')
+local now = tonumber(t[1]) * 1000 + math.floor(tonumber(t[2]) / 1000)
+local window = tonumber(ARGV[1])
+redis.call('ZREMRANGEBYSCORE', KEYS[1], '-inf', now - window)
+redis.call('ZADD', KEYS[1], now, ARGV[4])
+-- Anahtar, penceredeki en yeni üye düştüğünde silinir (en yeni skor + pencere).
+local newest = redis.call('ZRANGE', KEYS[1], -1, -1, 'WITHSCORES')
+redis.call('PEXPIREAT', KEYS[1], tonumber(newest[2]) + window)
+local count = redis.call('ZCARD', KEYS[1])
+if count >= tonumber(ARGV[2]) then
+  redis.call('HSET', KEYS[2], 'failures', count, 'locked_at', t[1])
+  redis.call('EXPIRE', KEYS[2], ARGV[3])
+  redis.call('DEL', KEYS[1])
+  return { count, 1 }
+end
+return { count, 0 }
+"#;
+
+/// Kilitlenebilen kaynak: hesap (AOR) veya istek kaynağı (IP).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LockScope {
+    Aor,
+    SourceIp,
+}
+
+impl LockScope {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Aor => "aor",
+            Self::SourceIp => "source_ip",
+        }
+    }
+
+    pub fn parse(raw: &str) -> Option<Self> {
+        match raw {
+            "aor" => Some(Self::Aor),
+            "source_ip" => Some(Self::SourceIp),
+            _ => None,
+        }
+    }
+}
+
+/// Kayan penceredeki hata eşiği; 0 = kapalı.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LockoutRule {
+    pub threshold: u32,
+    pub window: Duration,
+    pub lockout: Duration,
+}
+
+/// Başarısız deneme kaydının sonucu.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FailureOutcome {
+    pub failures: u64,
+    pub locked: bool,
+}
+
+/// Etkin bir kilit (admin API çıktısı).
+#[derive(Debug, Clone, Serialize)]
+pub struct Lockout {
+    pub scope: LockScope,
+    pub subject: String,
+    pub failures: u64,
+    pub locked_at: i64,
+    pub remaining_secs: u64,
+}
+
+/// Redis'te tutulan, replikalar arasında ortak kaba kuvvet (brute-force) sayaçları ve kilitleri.
+#[derive(Clone)]
+pub struct LockoutStore {
+    redis: RedisConn,
+    record_script: Arc<Script>,
+}
+
+impl LockoutStore {
+    pub fn new(redis: RedisConn) -> Self {
+        Self { redis, record_script: Arc::new(Script::new(RECORD_FAILURE_SCRIPT)) }
+    }
+
+    fn key(prefix: &str, scope: LockScope, subject: &str) -> String {
+        format!("{}:{}:{}", prefix, scope.as_str(), subject)
+    }
+
+    /// Verilen kaynaklardan ilk kilitli olanı ve kalan süresini döner.
+    pub async fn locked(&self, subjects: &[(LockScope, &str)]) -> anyhow::Result<Option<(LockScope, Duration)>> {
+        if subjects.is_empty() {
+            return Ok(None);
+        }
+        let mut pipe = redis::pipe();
+        for (scope, subject) in subjects {
+            pipe.ttl(Self::key(LOCK_PREFIX, *scope, subject));
+        }
+        let mut conn = self.redis.clone();
+        let ttls: Vec<i64> = pipe.query_async(&mut conn).await?;
+        Ok(subjects.iter().zip(ttls)
+            .find(|(_, ttl)| *ttl > 0)
+            .map(|((scope, _), ttl)| (*scope, Duration::from_secs(ttl as u64))))
+    }
+
+    pub async fn record_failure(&self, scope: LockScope, subject: &str, rule: LockoutRule) -> anyhow::Result<FailureOutcome> {
+        let mut conn = self.redis.clone();
+        let (failures, locked): (u64, u8) = self.record_script
+            .key(Self::key(FAILURES_PREFIX, scope, subject))
+            .key(Self::key(LOCK_PREFIX, scope, subject))
+            .arg(rule.window.as_millis() as u64)
+            .arg(rule.threshold)
+            .arg(rule.lockout.as_secs().max(1))
+            .arg(format!("{:x}", random_u64()))
+            .invoke_async(&mut conn)
+            .await?;
+        Ok(FailureOutcome { failures, locked: locked == 1 })
+    }
+
+    /// Başarılı kimlik doğrulamasında hesabın hata sayacını sıfırlar.
+    pub async fn reset_failures(&self, scope: LockScope, subject: &str) -> anyhow::Result<()> {
+        let mut conn = self.redis.clone();
+        let _: () = conn.del(Self::key(FAILURES_PREFIX, scope, subject)).await?;
+        Ok(())
+    }
+
+    /// Kilidi ve hata sayacını siler; kilit vardıysa `true` döner.
+    pub async fn clear(&self, scope: LockScope, subject: &str) -> anyhow::Result<bool> {
+        let mut conn = self.redis.clone();
+        let (removed, _): (u32, u32) = redis::pipe()
+            .del(Self::key(LOCK_PREFIX, scope, subject))
+            .del(Self::key(FAILURES_PREFIX, scope, subject))
+            .query_async(&mut conn)
+            .await?;
+        Ok(removed > 0)
+    }
+
+    /// Etkin kilitleri listeler (SCAN; en fazla `limit` kayıt).
+    pub async fn list(&self, limit: usize) -> anyhow::Result<Vec<Lockout>> {
+        let mut conn = self.redis.clone();
+        let keys: Vec<String> = {
+            let mut iter = conn.scan_match::<_, String>(format!("{}:*", LOCK_PREFIX)).await?;
+            let mut keys = Vec::new();
+            while let Some(key) = iter.next_item().await {
+                keys.push(key);
+                if keys.len() >= limit {
+                    break;
+                }
+            }
+            keys
+        };
+
+        let subjects: Vec<(String, LockScope, String)> = keys
+            .into_iter()
+            .filter_map(|key| {
+                let (scope, subject) = key.strip_prefix(LOCK_PREFIX)?.strip_prefix(':')?.split_once(':')?;
+                let (scope, subject) = (LockScope::parse(scope)?, subject.to_string());
+                Some((key, scope, subject))
+            })
+            .collect();
+        if subjects.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        // Tüm kilitler tek pipeline ile okunur (kilit başına bir tur yerine).
+        let mut pipe = redis::pipe();
+        for (key, _, _) in &subjects {
+            pipe.hgetall(key).ttl(key);
+        }
+        let replies: Vec<(HashMap<String, String>, i64)> = {
+            let flat: Vec<redis::Value> = pipe.query_async(&mut conn).await?;
+            flat.chunks_exact(2)
+                .map(|pair| Ok((redis::from_redis_value(&pair[0])?, redis::from_redis_value(&pair[1])?)))
+                .collect::<redis::RedisResult<_>>()?
+        };
+
+        let mut lockouts = Vec::with_capacity(subjects.len());
+        for ((_, scope, subject), (fields, ttl)) in subjects.into_iter().zip(replies) {
+            if ttl <= 0 {
+                continue;
+            }
+            lockouts.push(Lockout {
+                scope,
+                subject,
+                failures: fields.get("failures").and_then(|v| v.parse().ok()).unwrap_or(0),
+                locked_at: fields.get("locked_at").and_then(|v| v.parse().ok()).unwrap_or(0),
+                remaining_secs: ttl as u64,
+            });
+        }
+        Ok(lockouts)
+    }
+}
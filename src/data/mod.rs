@@ -1 +1,5 @@
-pub mod store;
\ No newline at end of file
+pub mod credential_cache;
+pub mod lockout;
+pub mod rate_limit;
+pub mod store;
+pub mod tenant_networks;
\ No newline at end of file
@@ -0,0 +1,118 @@
+// src/data/rate_limit.rs
+use crate::data::store::RedisConn;
+use redis::Script;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Tüm kovaları tek atomik adımda değerlendirir: biri bile boşsa hiçbirinden jeton
+/// düşülmez. Zaman Redis'in saatinden alınır; replikalar arası saat kayması etkisizdir.
+/// ARGV: kova başına (dakikada jeton, kapasite), ardından en uzun TTL (ms).
+/// Dönüş: {reddeden kova sırası (0 = izin), bekleme ms}.
+const TOKEN_BUCKET_SCRIPT: &str = r#"
+local t = redis.call('TIME')
+local now = tonumber(t[1]) * 1000 + math.floor(tonumber(t[2]) / 1000)
+local max_ttl = tonumber(ARGV[#KEYS * 2 + 1])
+local buckets = {}
+local denied, wait = 0, 0
+for i, key in ipairs(KEYS) do
+  local rate = tonumber(ARGV[i * 2 - 1]) / 60000
+  local burst = tonumber(ARGV[i * 2])
+  local state = redis.call('HMGET', key, 'tokens', 'ts')
+  local tokens = tonumber(state[1]) or burst
+  local ts = tonumber(state[2]) or now
+  tokens = math.min(burst, tokens + math.max(0, now - ts) * rate)
+  buckets[i] = { tokens = tokens, rate = rate, burst = burst }
+  if tokens < 1 and denied == 0 then
+    denied = i
+    wait = math.ceil((1 - tokens) / rate)
+  end
+end
+for i, key in ipairs(KEYS) do
+  local b = buckets[i]
+  if denied == 0 then b.tokens = b.tokens - 1 end
+  redis.call('HSET', key, 'tokens', tostring(b.tokens), 'ts', now)
+  redis.call('PEXPIRE', key, math.min(max_ttl, math.ceil(b.burst / b.rate) + 1000))
+end
+return { denied, wait }
+"#;
+
+/// Kova anahtarlarının en uzun ömrü. Tam dolum süresi bunu aşan (düşük hızlı, büyük
+/// kapasiteli) kovalar bu süre boyunca istek görmezse dolu kabul edilerek silinir.
+const MAX_BUCKET_TTL: Duration = Duration::from_secs(3600);
+
+/// Hız sınırının uygulandığı boyut. AOR ve tenant kovaları URI'deki host'tan değil,
+/// kimlik bilgisinin doğrulandığı kanonik realm'den (`PolicySet::canonical_realm`) kurulur;
+/// host'u değiştiren istemci yeni kova açamaz.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LimitScope {
+    Aor,
+    SourceIp,
+    Tenant,
+}
+
+impl LimitScope {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Aor => "aor",
+            Self::SourceIp => "source_ip",
+            Self::Tenant => "tenant",
+        }
+    }
+}
+
+/// Token-bucket: dakikada `per_min` jeton dolar, en fazla `burst` birikir.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BucketLimit {
+    pub per_min: u32,
+    pub burst: u32,
+}
+
+impl BucketLimit {
+    pub fn is_enabled(&self) -> bool {
+        self.per_min > 0 && self.burst > 0
+    }
+}
+
+/// Reddedilen isteğin hangi kovaya takıldığı ve ne kadar sonra tekrar denenebileceği.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Limited {
+    pub scope: LimitScope,
+    pub retry_after: Duration,
+}
+
+/// Redis üzerinde tutulan, replikalar arasında paylaşılan REGISTER hız sınırları.
+#[derive(Clone)]
+pub struct RateLimiter {
+    redis: RedisConn,
+    script: Arc<Script>,
+}
+
+impl RateLimiter {
+    pub fn new(redis: RedisConn) -> Self {
+        Self { redis, script: Arc::new(Script::new(TOKEN_BUCKET_SCRIPT)) }
+    }
+
+    /// Etkin kovalardan birer jeton düşer; biri boşsa `Limited` döner ve hiçbirine dokunmaz.
+    pub async fn check(&self, buckets: &[(LimitScope, String, BucketLimit)]) -> anyhow::Result<Option<Limited>> {
+        let active: Vec<_> = buckets.iter().filter(|(_, _, limit)| limit.is_enabled()).collect();
+        if active.is_empty() {
+            return Ok(None);
+        }
+
+        let mut invocation = self.script.prepare_invoke();
+        for (scope, id, limit) in &active {
+            invocation
+                .key(format!("rl:register:{}:{}", scope.as_str(), id))
+                .arg(limit.per_min)
+                .arg(limit.burst);
+        }
+        invocation.arg(MAX_BUCKET_TTL.as_millis() as u64);
+        let mut conn = self.redis.clone();
+        let (denied, wait_ms): (usize, u64) = invocation.invoke_async(&mut conn).await?;
+
+        Ok(denied.checked_sub(1).and_then(|i| active.get(i)).map(|(scope, _, _)| Limited {
+            scope: *scope,
+            retry_after: Duration::from_millis(wait_ms),
+        }))
+    }
+}
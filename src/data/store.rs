@@ -1,70 +1,366 @@
 // src/data/store.rs
 use redis::AsyncCommands;
+use std::collections::HashMap;
 use redis::aio::ConnectionManager;
-use tracing::{info, debug, warn, instrument};
-use sentiric_sip_core::utils as sip_utils;
+use redis::Script;
+use serde::Serialize;
+use std::sync::Arc;
+use tracing::{info, debug, instrument};
 
 // Artık Arc<Mutex<...>> kullanmıyoruz, ConnectionManager kendi içinde güvenlidir ve kopmaları yönetir.
 pub type RedisConn = ConnectionManager;
 
+/// Yeni bağlamayı kota kontrolüyle birlikte atomik olarak yazar.
+/// KEYS: AOR bağlama kümesi, bağlama hash'i, tenant kümesi, eski (tek bağlamalı) anahtarlar,
+/// ardından atılabilecek (AOR'un mevcut) bağlama hash'leri.
+/// ARGV: cihaz, süre, AOR sınırı, tenant sınırı, en eskiyi at (1/0), bağlama anahtarı öneki,
+/// tenant üyesi öneki, ardından hash alan/değer çiftleri.
+/// Dönüş: {yazıldı mı (1/0, -1 = yeniden dene), kota kapsamı veya atılan cihazlar (\n ile),
+/// AOR sayısı, tenant sayısı}.
+///
+/// Tenant kümesi AOR anahtarlarından farklı slot'tadır; script yalnızca tek düğümlü
+/// (cluster olmayan) Redis'te çalışır, `ensure_standalone` bunu açılışta doğrular.
+const REGISTER_SCRIPT: &str = r#"
+local now = tonumber(redis.call('TIME')[1])
+local device, expires = ARGV[1], tonumber(ARGV[2])
+local max_aor, max_tenant, evict = tonumber(ARGV[3]), tonumber(ARGV[4]), ARGV[5] == '1'
+redis.call('ZREMRANGEBYSCORE', KEYS[1], '-inf', now)
+redis.call('ZREMRANGEBYSCORE', KEYS[3], '-inf', now)
+local aor_count, tenant_count = redis.call('ZCARD', KEYS[1]), redis.call('ZCARD', KEYS[3])
+local victims = {}
+if not redis.call('ZSCORE', KEYS[1], device) then
+  if max_aor > 0 and aor_count >= max_aor then
+    if not evict then return { 0, 'aor', aor_count, tenant_count } end
+    victims = redis.call('ZRANGE', KEYS[1], 0, aor_count - max_aor)
+  end
+  if max_tenant > 0 and tenant_count - #victims >= max_tenant then
+    return { 0, 'tenant', aor_count, tenant_count }
+  end
+end
+-- Atılacak her bağlamanın anahtarı KEYS içinde bildirilmiş olmalı. Küme istemci okuduktan
+-- sonra değiştiyse hiçbir bağlamaya dokunmadan yeniden deneme istenir.
+local declared = {}
+for i = 6, #KEYS do declared[KEYS[i]] = true end
+for _, victim in ipairs(victims) do
+  if not declared[ARGV[6] .. victim] then return { -1, '', aor_count, tenant_count } end
+end
+for _, victim in ipairs(victims) do
+  redis.call('ZREM', KEYS[1], victim)
+  redis.call('DEL', ARGV[6] .. victim)
+  redis.call('ZREM', KEYS[3], ARGV[7] .. victim)
+end
+redis.call('DEL', KEYS[2], KEYS[4], KEYS[5])
+redis.call('HSET', KEYS[2], unpack(ARGV, 8))
+redis.call('EXPIRE', KEYS[2], expires)
+redis.call('ZADD', KEYS[1], now + expires, device)
+redis.call('ZADD', KEYS[3], now + expires, ARGV[7] .. device)
+for _, key in ipairs({ KEYS[1], KEYS[3] }) do
+  if redis.call('TTL', key) < expires then redis.call('EXPIRE', key, expires) end
+end
+return { 1, table.concat(victims, '\n'), redis.call('ZCARD', KEYS[1]), redis.call('ZCARD', KEYS[3]) }
+"#;
+
+/// Küme okunduktan sonra eşzamanlı değişirse register script'i en fazla bu kadar denenir.
+const REGISTER_ATTEMPTS: usize = 3;
+
+/// REGISTER isteğinden gelen, bağlamayı tanımlayan ek bilgiler.
+#[derive(Debug, Clone, Default)]
+pub struct BindingInfo {
+    pub call_id: Option<String>,
+    pub instance: Option<String>,
+    pub user_agent: Option<String>,
+}
+
+impl BindingInfo {
+    /// AOR içindeki cihaz kimliği: `+sip.instance` varsa o, yoksa Contact URI.
+    pub fn device_id(&self, contact_uri: &str) -> String {
+        self.instance.clone().unwrap_or_else(|| contact_uri.trim().trim_matches(|c| c == '<' || c == '>').to_string())
+    }
+}
+
+/// `sip_binding:<kullanıcı>:<cihaz>` hash'inde tutulan bağlama ayrıntıları.
+#[derive(Debug, Clone, Serialize)]
+pub struct BindingMeta {
+    pub device_id: String,
+    pub contact: String,
+    pub tenant: String,
+    pub call_id: Option<String>,
+    pub instance: Option<String>,
+    pub user_agent: Option<String>,
+    /// Son başarılı kimlik doğrulamasının zamanı (unix saniye).
+    pub authenticated_at: i64,
+    /// Kimlik doğrulamasız (degraded) yenileme sayısı.
+    pub degraded_refreshes: u64,
+}
+
+/// Bağlama sayısı sınırları (0 = sınırsız).
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BindingLimits {
+    pub max_per_aor: u32,
+    pub max_per_tenant: u32,
+    /// AOR sınırı doluyken süresi en erken dolacak bağlamayı atarak yer aç.
+    pub evict_oldest: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QuotaScope {
+    Aor,
+    Tenant,
+}
+
+impl QuotaScope {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Aor => "aor",
+            Self::Tenant => "tenant",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum RegisterOutcome {
+    Stored { evicted: Vec<String>, aor_bindings: u64, tenant_bindings: u64 },
+    QuotaExceeded { scope: QuotaScope, aor_bindings: u64, tenant_bindings: u64 },
+}
+
+/// Bağlamalar AOR (`kullanıcı@realm`, küçük harf; bkz. `policy::aor`) ile anahtarlanır.
 #[derive(Clone)]
 pub struct RegistrationStore {
     redis: RedisConn,
+    register_script: Arc<Script>,
 }
 
 impl RegistrationStore {
     pub fn new(redis: RedisConn) -> Self {
-        Self { redis }
+        Self { redis, register_script: Arc::new(Script::new(REGISTER_SCRIPT)) }
     }
 
-    fn generate_key(&self, raw_uri: &str) -> String {
-        let username = sip_utils::extract_username_from_uri(raw_uri);
-        if username.is_empty() {
-             warn!(event="URI_PARSE_WARN", uri=%raw_uri, "Username extraction failed, using raw URI");
-             return format!("sip_reg:{}", raw_uri);
+    /// Tenant kümeleri AOR anahtarlarıyla aynı slot'ta olamaz; register script'i Redis
+    /// Cluster'da çalışmaz. Açılışta cluster modu reddedilir.
+    pub async fn ensure_standalone(&self) -> anyhow::Result<()> {
+        let mut conn = self.redis.clone();
+        let info: String = redis::cmd("INFO").arg("cluster").query_async(&mut conn).await?;
+        if info.lines().any(|line| line.trim() == "cluster_enabled:1") {
+            anyhow::bail!("Redis Cluster desteklenmiyor: kayıt script'leri AOR ve tenant anahtarlarına birlikte erişir; tek düğümlü Redis (veya Sentinel) kullanın");
         }
-        format!("sip_reg:{}", username)
+        Ok(())
     }
 
-    #[instrument(skip(self), fields(key))]
-    pub async fn register_user(&self, sip_uri: &str, contact_uri: &str, expires: i32) -> anyhow::Result<()> {
-        let key = self.generate_key(sip_uri);
-        // ConnectionManager ucuz bir şekilde kopyalanabilir (clone), içindeki havuzu paylaşır.
-        let mut conn = self.redis.clone();
+    fn bindings_key(aor: &str) -> String {
+        format!("sip_bindings:{}", aor)
+    }
+
+    fn binding_key(aor: &str, device_id: &str) -> String {
+        format!("sip_binding:{}:{}", aor, device_id)
+    }
+
+    fn tenant_key(tenant: &str) -> String {
+        format!("sip_tenant_bindings:{}", tenant)
+    }
+
+    fn tenant_member(aor: &str, device_id: &str) -> String {
+        format!("{}|{}", aor, device_id)
+    }
+
+    /// Tek bağlamalı eski şemanın (kullanıcı adıyla anahtarlanmış) anahtarları; geçiş
+    /// süresince okunur ve temizlenir.
+    fn legacy_keys(aor: &str) -> [String; 2] {
+        let username = aor.rsplit_once('@').map_or(aor, |(user, _)| user);
+        [format!("sip_reg:{}", username), format!("sip_reg_meta:{}", username)]
+    }
+
+    /// Cihazın bağlamasını yazar (`expires <= 0` ise siler). Yeni cihaz sınırı aşıyorsa
+    /// `limits` politikasına göre reddedilir ya da AOR'un en eski bağlaması atılır.
+    #[instrument(skip_all)]
+    pub async fn register_user(&self, aor: &str, contact_uri: &str, expires: i32, info: &BindingInfo, tenant: &str, limits: BindingLimits) -> anyhow::Result<RegisterOutcome> {
+        let device_id = info.device_id(contact_uri);
 
         if expires <= 0 {
-             let _: () = conn.del(&key).await?;
-             info!(event="SIP_UNREGISTER_EXPIRE", key=%key, "Kayıt süresi dolduğu için silindi");
-        } else {
-            let _: () = conn.set_ex(&key, contact_uri, expires as u64).await?;
-            debug!(event="SIP_REGISTER_STORED", key=%key, contact=%contact_uri, ttl=%expires, "Kayıt Redis'e yazıldı");
+            self.remove_binding(aor, &device_id).await?;
+            info!(event="SIP_UNREGISTER_EXPIRE", aor=%aor, device=%device_id, "Kayıt süresi dolduğu için silindi");
+            let (aor_bindings, tenant_bindings) = self.counts(aor, tenant).await?;
+            return Ok(RegisterOutcome::Stored { evicted: Vec::new(), aor_bindings, tenant_bindings });
+        }
+
+        let fields = [
+            ("contact", contact_uri.to_string()),
+            ("tenant", tenant.to_string()),
+            ("call_id", info.call_id.clone().unwrap_or_default()),
+            ("instance", info.instance.clone().unwrap_or_default()),
+            ("user_agent", info.user_agent.clone().unwrap_or_default()),
+            ("authenticated_at", chrono::Utc::now().timestamp().to_string()),
+            ("degraded_refreshes", "0".to_string()),
+        ];
+        let evicting = limits.evict_oldest && limits.max_per_aor > 0;
+        let mut conn = self.redis.clone();
+        for _ in 0..REGISTER_ATTEMPTS {
+            // Atılabilecek bağlamaların anahtarları script'e KEYS olarak bildirilir.
+            let current: Vec<String> = if evicting { conn.zrange(Self::bindings_key(aor), 0, -1).await? } else { Vec::new() };
+            let [legacy, legacy_meta] = Self::legacy_keys(aor);
+            let mut invocation = self.register_script.prepare_invoke();
+            invocation
+                .key(Self::bindings_key(aor))
+                .key(Self::binding_key(aor, &device_id))
+                .key(Self::tenant_key(tenant))
+                .key(legacy)
+                .key(legacy_meta);
+            for device in &current {
+                invocation.key(Self::binding_key(aor, device));
+            }
+            invocation
+                .arg(&device_id)
+                .arg(expires)
+                .arg(limits.max_per_aor)
+                .arg(limits.max_per_tenant)
+                .arg(if limits.evict_oldest { 1 } else { 0 })
+                .arg(Self::binding_key(aor, ""))
+                .arg(Self::tenant_member(aor, ""));
+            for (field, value) in &fields {
+                invocation.arg(*field).arg(value);
+            }
+            let (stored, detail, aor_bindings, tenant_bindings): (i8, String, u64, u64) = invocation.invoke_async(&mut conn).await?;
+
+            match stored {
+                -1 => continue,
+                0 => {
+                    let scope = if detail == "tenant" { QuotaScope::Tenant } else { QuotaScope::Aor };
+                    return Ok(RegisterOutcome::QuotaExceeded { scope, aor_bindings, tenant_bindings });
+                }
+                _ => {
+                    let evicted: Vec<String> = detail.split('\n').filter(|d| !d.is_empty()).map(str::to_string).collect();
+                    debug!(event="SIP_REGISTER_STORED", aor=%aor, device=%device_id, contact=%contact_uri, ttl=%expires, bindings=aor_bindings, "Kayıt Redis'e yazıldı");
+                    return Ok(RegisterOutcome::Stored { evicted, aor_bindings, tenant_bindings });
+                }
+            }
         }
+        anyhow::bail!("AOR bağlama kümesi eşzamanlı değişti; kayıt {} denemede yazılamadı", REGISTER_ATTEMPTS)
+    }
+
+    /// AOR'un ve tenant'ın etkin bağlama sayıları.
+    async fn counts(&self, aor: &str, tenant: &str) -> anyhow::Result<(u64, u64)> {
+        let mut conn = self.redis.clone();
+        let now = chrono::Utc::now().timestamp();
+        Ok(redis::pipe()
+            .zcount(Self::bindings_key(aor), now, "+inf")
+            .zcount(Self::tenant_key(tenant), now, "+inf")
+            .query_async(&mut conn)
+            .await?)
+    }
+
+    async fn remove_binding(&self, aor: &str, device_id: &str) -> anyhow::Result<()> {
+        let binding_key = Self::binding_key(aor, device_id);
+        let mut conn = self.redis.clone();
+        let tenant: Option<String> = conn.hget(&binding_key, "tenant").await?;
+        let mut pipe = redis::pipe();
+        pipe.atomic()
+            .del(&binding_key).ignore()
+            .zrem(Self::bindings_key(aor), device_id).ignore();
+        if let Some(tenant) = tenant {
+            pipe.zrem(Self::tenant_key(&tenant), Self::tenant_member(aor, device_id)).ignore();
+        }
+        let _: () = pipe.query_async(&mut conn).await?;
         Ok(())
     }
 
-    #[instrument(skip(self), fields(key))]
-    pub async fn unregister_user(&self, sip_uri: &str) -> anyhow::Result<()> {
-        let key = self.generate_key(sip_uri);
+    /// Cihazın geçerli (süresi dolmamış) bağlamasını döner.
+    pub async fn binding(&self, aor: &str, device_id: &str) -> anyhow::Result<Option<BindingMeta>> {
         let mut conn = self.redis.clone();
-        let _: () = conn.del(&key).await?;
-        info!(event="SIP_UNREGISTER_MANUAL", key=%key, "Kullanıcı manuel silindi");
+        let fields: HashMap<String, String> = conn.hgetall(Self::binding_key(aor, device_id)).await?;
+        Ok(Self::parse_meta(device_id, fields))
+    }
+
+    /// AOR'un tüm geçerli bağlamaları; süresi en geç dolacak (en son yenilenen) önce.
+    pub async fn bindings(&self, aor: &str) -> anyhow::Result<Vec<BindingMeta>> {
+        let mut conn = self.redis.clone();
+        let now = chrono::Utc::now().timestamp();
+        let devices: Vec<String> = conn.zrevrangebyscore(Self::bindings_key(aor), "+inf", now).await?;
+        if devices.is_empty() {
+            return Ok(Vec::new());
+        }
+        let mut pipe = redis::pipe();
+        for device in &devices {
+            pipe.hgetall(Self::binding_key(aor, device));
+        }
+        let all: Vec<HashMap<String, String>> = pipe.query_async(&mut conn).await?;
+        Ok(devices.iter().zip(all).filter_map(|(device, fields)| Self::parse_meta(device, fields)).collect())
+    }
+
+    fn parse_meta(device_id: &str, fields: HashMap<String, String>) -> Option<BindingMeta> {
+        let contact = fields.get("contact").cloned()?;
+        let non_empty = |name: &str| fields.get(name).filter(|v| !v.is_empty()).cloned();
+        Some(BindingMeta {
+            device_id: device_id.to_string(),
+            contact,
+            tenant: fields.get("tenant").cloned().unwrap_or_default(),
+            call_id: non_empty("call_id"),
+            instance: non_empty("instance"),
+            user_agent: non_empty("user_agent"),
+            authenticated_at: fields.get("authenticated_at").and_then(|v| v.parse().ok()).unwrap_or(0),
+            degraded_refreshes: fields.get("degraded_refreshes").and_then(|v| v.parse().ok()).unwrap_or(0),
+        })
+    }
+
+    /// Kimlik doğrulaması yapılamadan (degraded mode) bağlamanın süresini uzatır;
+    /// `authenticated_at` değişmez.
+    #[instrument(skip_all)]
+    pub async fn refresh_degraded(&self, aor: &str, binding: &BindingMeta, expires: i32) -> anyhow::Result<()> {
+        let binding_key = Self::binding_key(aor, &binding.device_id);
+        let expires_at = chrono::Utc::now().timestamp() + expires as i64;
+        let mut conn = self.redis.clone();
+        let _: () = redis::pipe()
+            .atomic()
+            .hincr(&binding_key, "degraded_refreshes", 1).ignore()
+            .expire(&binding_key, expires as i64).ignore()
+            .zadd(Self::bindings_key(aor), &binding.device_id, expires_at).ignore()
+            .zadd(Self::tenant_key(&binding.tenant), Self::tenant_member(aor, &binding.device_id), expires_at).ignore()
+            .query_async(&mut conn)
+            .await?;
+        debug!(event="SIP_REGISTER_DEGRADED_STORED", aor=%aor, device=%binding.device_id, ttl=%expires, "Kayıt kimlik doğrulamasız uzatıldı");
         Ok(())
     }
 
-    #[instrument(skip(self), fields(key))]
-    pub async fn lookup_user(&self, sip_uri: &str) -> Option<String> {
-        let key = self.generate_key(sip_uri);
+    /// AOR'un tüm bağlamalarını siler; silinen bağlama sayısını döner.
+    #[instrument(skip_all)]
+    pub async fn unregister_user(&self, aor: &str) -> anyhow::Result<usize> {
         let mut conn = self.redis.clone();
-        
-        match conn.get::<_, String>(&key).await {
-            Ok(contact) => {
-                debug!(event="SIP_LOCATION_FOUND", key=%key, contact=%contact, "Konum bulundu");
-                Some(contact)
-            },
-            Err(_) => {
-                warn!(event="SIP_LOCATION_MISS", key=%key, "Konum bulunamadı (Offline)");
-                None
-            }
+        let devices: Vec<String> = conn.zrange(Self::bindings_key(aor), 0, -1).await?;
+        for device in &devices {
+            self.remove_binding(aor, device).await?;
+        }
+        let [legacy, legacy_meta] = Self::legacy_keys(aor);
+        let _: () = conn.del(vec![Self::bindings_key(aor), legacy, legacy_meta]).await?;
+        info!(event="SIP_UNREGISTER_MANUAL", aor=%aor, devices=devices.len(), "Kullanıcı manuel silindi");
+        Ok(devices.len())
+    }
+
+    /// Aranabilir contact'lar (en son yenilenen önce). Eski şemadan kalan tek bağlama
+    /// da geçiş süresince döner.
+    #[instrument(skip_all)]
+    pub async fn lookup_user(&self, aor: &str) -> anyhow::Result<Vec<BindingMeta>> {
+        let bindings = self.bindings(aor).await?;
+        if !bindings.is_empty() {
+            debug!(event="SIP_LOCATION_FOUND", aor=%aor, bindings=bindings.len(), "Konum bulundu");
+            return Ok(bindings);
         }
+
+        let [legacy, _] = Self::legacy_keys(aor);
+        let mut conn = self.redis.clone();
+        let contact: Option<String> = conn.get(&legacy).await?;
+        Ok(contact.map(|contact| BindingMeta {
+            device_id: contact.clone(),
+            contact,
+            tenant: String::new(),
+            call_id: None,
+            instance: None,
+            user_agent: None,
+            authenticated_at: 0,
+            degraded_refreshes: 0,
+        }).into_iter().collect())
     }
-}
\ No newline at end of file
+
+    /// Tenant'ın etkin kayıt sayısı.
+    pub async fn tenant_usage(&self, tenant: &str) -> anyhow::Result<u64> {
+        let mut conn = self.redis.clone();
+        Ok(conn.zcount(Self::tenant_key(tenant), chrono::Utc::now().timestamp(), "+inf").await?)
+    }
+}
@@ -0,0 +1,252 @@
+// src/data/tenant_networks.rs
+use crate::data::store::RedisConn;
+use anyhow::{bail, Context, Result};
+use arc_swap::ArcSwap;
+use redis::AsyncCommands;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::net::IpAddr;
+use std::str::FromStr;
+use std::sync::Arc;
+use tracing::{info, warn};
+
+/// `adres/önek` biçiminde ağ bloğu; önek yoksa tek adres (/32, /128).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cidr {
+    network: IpAddr,
+    prefix: u8,
+}
+
+impl Cidr {
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        match (self.network, ip.to_canonical()) {
+            (IpAddr::V4(net), IpAddr::V4(ip)) => mask_v4(u32::from(ip), self.prefix) == u32::from(net),
+            (IpAddr::V6(net), IpAddr::V6(ip)) => mask_v6(u128::from(ip), self.prefix) == u128::from(net),
+            _ => false,
+        }
+    }
+}
+
+fn mask_v4(addr: u32, prefix: u8) -> u32 {
+    if prefix == 0 { 0 } else { addr & (!0u32 << (32 - prefix as u32)) }
+}
+
+fn mask_v6(addr: u128, prefix: u8) -> u128 {
+    if prefix == 0 { 0 } else { addr & (!0u128 << (128 - prefix as u32)) }
+}
+
+impl FromStr for Cidr {
+    type Err = anyhow::Error;
+
+    fn from_str(raw: &str) -> Result<Self> {
+        let raw = raw.trim();
+        let (addr, prefix) = match raw.split_once('/') {
+            Some((addr, prefix)) => (addr, Some(prefix)),
+            None => (raw, None),
+        };
+        let addr: IpAddr = addr.parse().with_context(|| format!("'{}' geçerli bir IP değil", raw))?;
+        let addr = addr.to_canonical();
+        let max = if addr.is_ipv4() { 32 } else { 128 };
+        let prefix = match prefix {
+            Some(p) => p.parse::<u8>().ok().filter(|p| *p <= max).with_context(|| format!("'{}' için önek 0-{} olmalı", raw, max))?,
+            None => max,
+        };
+        // Host bitleri atılır: 10.1.2.3/8 -> 10.0.0.0/8.
+        let network = match addr {
+            IpAddr::V4(v4) => IpAddr::V4(mask_v4(u32::from(v4), prefix).into()),
+            IpAddr::V6(v6) => IpAddr::V6(mask_v6(u128::from(v6), prefix).into()),
+        };
+        Ok(Self { network, prefix })
+    }
+}
+
+impl fmt::Display for Cidr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.network, self.prefix)
+    }
+}
+
+/// Bir tenant'ın kayıt olabileceği ağlar. Deny önce değerlendirilir; allow doluysa
+/// yalnızca listedeki ağlardan kayıt kabul edilir.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NetworkPolicy {
+    pub allow: Vec<String>,
+    pub deny: Vec<String>,
+    #[serde(skip)]
+    parsed: (Vec<Cidr>, Vec<Cidr>),
+}
+
+impl NetworkPolicy {
+    /// CIDR'ları doğrular ve normalize eder (ör. `10.1.2.3/8` -> `10.0.0.0/8`).
+    pub fn compile(mut self) -> Result<Self> {
+        let parse = |list: &[String]| list.iter().map(|c| c.parse::<Cidr>()).collect::<Result<Vec<_>>>();
+        let allow = parse(&self.allow)?;
+        let deny = parse(&self.deny)?;
+        if allow.is_empty() && deny.is_empty() {
+            bail!("allow veya deny listesinden en az biri dolu olmalı");
+        }
+        self.allow = allow.iter().map(Cidr::to_string).collect();
+        self.deny = deny.iter().map(Cidr::to_string).collect();
+        self.parsed = (allow, deny);
+        Ok(self)
+    }
+
+    /// Kaynak adresi değerlendirir; ret nedeni döner.
+    pub fn check(&self, source: Option<IpAddr>) -> Result<(), &'static str> {
+        let (allow, deny) = &self.parsed;
+        let Some(ip) = source else {
+            return if allow.is_empty() { Ok(()) } else { Err("source_unknown") };
+        };
+        if deny.iter().any(|c| c.contains(ip)) {
+            return Err("denied_network");
+        }
+        if !allow.is_empty() && !allow.iter().any(|c| c.contains(ip)) {
+            return Err("not_in_allowlist");
+        }
+        Ok(())
+    }
+}
+
+/// Tenant -> ağ politikası eşlemesi. Kalıcı kopya Redis hash'inde (`alan = tenant`,
+/// `değer = NetworkPolicy JSON`) tutulur; her replika periyodik olarak yerel kopyasını yeniler.
+#[derive(Clone)]
+pub struct TenantNetworks {
+    redis: RedisConn,
+    hash_key: String,
+    current: Arc<ArcSwap<HashMap<String, NetworkPolicy>>>,
+}
+
+impl TenantNetworks {
+    pub fn new(redis: RedisConn, hash_key: String) -> Self {
+        Self { redis, hash_key, current: Arc::new(ArcSwap::from_pointee(HashMap::new())) }
+    }
+
+    pub fn get(&self, tenant: &str) -> Option<NetworkPolicy> {
+        self.current.load().get(&tenant.to_ascii_lowercase()).cloned()
+    }
+
+    pub fn all(&self) -> HashMap<String, NetworkPolicy> {
+        (**self.current.load()).clone()
+    }
+
+    pub async fn reload(&self) -> Result<usize> {
+        let mut conn = self.redis.clone();
+        let raw: HashMap<String, String> = conn.hgetall(&self.hash_key).await
+            .context("Tenant ağ listeleri okunamadı")?;
+
+        let mut next = HashMap::new();
+        for (tenant, json) in raw {
+            match serde_json::from_str::<NetworkPolicy>(&json).map_err(anyhow::Error::from).and_then(NetworkPolicy::compile) {
+                Ok(policy) => {
+                    next.insert(tenant.to_ascii_lowercase(), policy);
+                }
+                Err(e) => warn!(event="TENANT_NETWORKS_PARSE_FAIL", tenant=%tenant, error=%e, "Tenant ağ listesi ayrıştırılamadı, atlanıyor"),
+            }
+        }
+        let loaded = next.len();
+        self.current.store(Arc::new(next));
+        Ok(loaded)
+    }
+
+    /// Tenant'ın listesini Redis'e yazar ve yerel kopyayı hemen yeniler.
+    pub async fn set(&self, tenant: &str, policy: NetworkPolicy) -> Result<NetworkPolicy> {
+        let policy = policy.compile()?;
+        let mut conn = self.redis.clone();
+        let _: () = conn.hset(&self.hash_key, tenant.to_ascii_lowercase(), serde_json::to_string(&policy)?).await?;
+        self.reload().await?;
+        Ok(policy)
+    }
+
+    pub async fn remove(&self, tenant: &str) -> Result<bool> {
+        let mut conn = self.redis.clone();
+        let removed: u32 = conn.hdel(&self.hash_key, tenant.to_ascii_lowercase()).await?;
+        self.reload().await?;
+        Ok(removed > 0)
+    }
+
+    /// Redis'teki listeleri periyodik olarak yükler; hata olursa mevcut kopya korunur.
+    pub fn spawn_refresh(&self, every: std::time::Duration) {
+        let networks = self.clone();
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(every);
+            let mut last = None;
+            loop {
+                ticker.tick().await;
+                match networks.reload().await {
+                    Ok(loaded) => {
+                        if last != Some(loaded) {
+                            info!(event="TENANT_NETWORKS_RELOADED", key=%networks.hash_key, tenants=loaded, "Tenant ağ listeleri yüklendi");
+                            last = Some(loaded);
+                        }
+                    }
+                    Err(e) => warn!(event="TENANT_NETWORKS_RELOAD_FAIL", error=%e, "Tenant ağ listeleri yüklenemedi, mevcut listeler korunuyor"),
+                }
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ip(raw: &str) -> IpAddr {
+        raw.parse().unwrap()
+    }
+
+    #[test]
+    fn zero_prefix_matches_whole_family() {
+        let any_v4: Cidr = "10.1.2.3/0".parse().unwrap();
+        assert_eq!(any_v4.to_string(), "0.0.0.0/0");
+        assert!(any_v4.contains(ip("203.0.113.7")));
+        assert!(!any_v4.contains(ip("2001:db8::1")));
+
+        let any_v6: Cidr = "2001:db8::1/0".parse().unwrap();
+        assert_eq!(any_v6.to_string(), "::/0");
+        assert!(any_v6.contains(ip("2001:db8::1")));
+    }
+
+    #[test]
+    fn full_prefix_is_a_single_host() {
+        let host: Cidr = "203.0.113.66/32".parse().unwrap();
+        assert!(host.contains(ip("203.0.113.66")));
+        assert!(!host.contains(ip("203.0.113.67")));
+        assert_eq!("203.0.113.66".parse::<Cidr>().unwrap().to_string(), "203.0.113.66/32");
+
+        let host6: Cidr = "2001:db8::66/128".parse().unwrap();
+        assert!(host6.contains(ip("2001:db8::66")));
+        assert!(!host6.contains(ip("2001:db8::67")));
+        assert_eq!("2001:db8::66".parse::<Cidr>().unwrap().to_string(), "2001:db8::66/128");
+    }
+
+    #[test]
+    fn host_bits_are_masked_and_mapped_v4_is_canonical() {
+        assert_eq!("10.1.2.3/8".parse::<Cidr>().unwrap().to_string(), "10.0.0.0/8");
+        assert_eq!("2001:db8:10:ff::1/48".parse::<Cidr>().unwrap().to_string(), "2001:db8:10::/48");
+
+        // IPv4-mapped IPv6 kaynaklar IPv4 ağlarıyla karşılaştırılır.
+        assert!("10.0.0.0/8".parse::<Cidr>().unwrap().contains(ip("::ffff:10.9.8.7")));
+        assert!(!"10.0.0.0/8".parse::<Cidr>().unwrap().contains(ip("::ffff:11.9.8.7")));
+    }
+
+    #[test]
+    fn invalid_prefixes_are_rejected() {
+        for raw in ["10.0.0.0/33", "2001:db8::/129", "10.0.0.0/-1", "10.0.0.0/", "not-an-ip/8", ""] {
+            assert!(raw.parse::<Cidr>().is_err(), "{:?}", raw);
+        }
+    }
+
+    #[test]
+    fn deny_wins_and_allow_list_requires_known_source() {
+        let policy = NetworkPolicy { allow: vec!["203.0.113.0/24".into()], deny: vec!["203.0.113.66".into()], ..Default::default() }
+            .compile()
+            .unwrap();
+        assert_eq!(policy.check(Some(ip("203.0.113.5"))), Ok(()));
+        assert_eq!(policy.check(Some(ip("203.0.113.66"))), Err("denied_network"));
+        assert_eq!(policy.check(Some(ip("198.51.100.1"))), Err("not_in_allowlist"));
+        assert_eq!(policy.check(None), Err("source_unknown"));
+        assert!(NetworkPolicy::default().compile().is_err());
+    }
+}
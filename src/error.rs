@@ -1,6 +1,23 @@
 // sentiric-registrar-service/src/error.rs
+use crate::contact::ContactRejection;
+use crate::data::lockout::LockScope;
+use crate::data::rate_limit::LimitScope;
+use crate::data::store::QuotaScope;
+use std::time::Duration;
 use thiserror::Error;
-use tonic::Status;
+use tonic::metadata::MetadataValue;
+use tonic::{Code, Status};
+
+/// Hata sınıfını çağırana taşıyan metadata anahtarları. Proxy bunlara bakarak
+/// 403 (kimlik reddi) ile 503 (altyapı hatası) arasında karar verir.
+pub const ERROR_REASON_HEADER: &str = "x-registrar-error";
+pub const ERROR_DEPENDENCY_HEADER: &str = "x-registrar-dependency";
+/// Hız sınırı / kilitte tekrar denemeden önce beklenecek süre (saniye); proxy SIP Retry-After'a taşır.
+pub const RETRY_AFTER_HEADER: &str = "retry-after";
+pub const RATE_LIMIT_SCOPE_HEADER: &str = "x-registrar-rate-limit-scope";
+pub const QUOTA_SCOPE_HEADER: &str = "x-registrar-quota-scope";
+/// Contact URI'nin reddedilme nedeni (ör. `loopback`, `too_long`).
+pub const CONTACT_REASON_HEADER: &str = "x-registrar-contact-reason";
 
 #[derive(Error, Debug)]
 pub enum ServiceError {
@@ -14,16 +31,123 @@ pub enum ServiceError {
     RedisError(#[from] redis::RedisError),
     #[error("I/O hatası: {0}")]
     Io(#[from] std::io::Error),
+    #[error("Kullanıcı bulunamadı: {0}")]
+    UserNotFound(String),
+    #[error("Kimlik bilgisi reddedildi: {0}")]
+    CredentialsRejected(String),
+    #[error("{dependency} erişilemez: {reason}")]
+    DependencyUnavailable { dependency: &'static str, reason: String },
+    #[error("{dependency} zaman aşımı: {reason}")]
+    DependencyTimeout { dependency: &'static str, reason: String },
+    #[error("Hız sınırı aşıldı ({}), {}ms sonra tekrar", scope.as_str(), retry_after.as_millis())]
+    RateLimited { scope: LimitScope, retry_after: Duration },
+    #[error("Çok sayıda başarısız deneme ({}), {}sn kilitli", scope.as_str(), retry_after.as_secs())]
+    LockedOut { scope: LockScope, retry_after: Duration },
+    #[error("Tarayıcı User-Agent'ı engellendi: {0}")]
+    ScannerBlocked(String),
+    #[error("Kayıt kotası dolu ({})", scope.as_str())]
+    QuotaExceeded { scope: QuotaScope },
+    #[error("Geçersiz Contact URI: {0}")]
+    InvalidContact(ContactRejection),
+}
+
+impl ServiceError {
+    /// User Service'in döndürdüğü durumu sınıflandırır: kimlik reddi mi, altyapı hatası mı?
+    pub fn from_user_service(status: Status) -> Self {
+        let reason = status.message().to_string();
+        match status.code() {
+            Code::NotFound => Self::UserNotFound(reason),
+            Code::PermissionDenied | Code::Unauthenticated | Code::InvalidArgument => Self::CredentialsRejected(reason),
+            Code::DeadlineExceeded | Code::Cancelled => Self::DependencyTimeout { dependency: "user-service", reason },
+            _ => Self::DependencyUnavailable { dependency: "user-service", reason },
+        }
+    }
+
+    /// Kimlik reddi değil, bağımlılık kaynaklı (tekrar denenebilir) hata mı?
+    pub fn is_dependency_failure(&self) -> bool {
+        matches!(self, Self::DependencyUnavailable { .. } | Self::DependencyTimeout { .. })
+    }
+
+    /// Çağırana metadata ile iletilen kararlı hata kodu.
+    pub fn reason(&self) -> &'static str {
+        match self {
+            Self::ConfigError(_) => "CONFIG_ERROR",
+            Self::GrpcTransportError(_) => "TRANSPORT_ERROR",
+            Self::GrpcStatus(_) => "UPSTREAM_STATUS",
+            Self::RedisError(_) => "STORE_ERROR",
+            Self::Io(_) => "IO_ERROR",
+            Self::UserNotFound(_) => "USER_NOT_FOUND",
+            Self::CredentialsRejected(_) => "CREDENTIALS_REJECTED",
+            Self::DependencyUnavailable { .. } => "DEPENDENCY_UNAVAILABLE",
+            Self::DependencyTimeout { .. } => "DEPENDENCY_TIMEOUT",
+            Self::RateLimited { .. } => "RATE_LIMITED",
+            Self::LockedOut { .. } => "LOCKED_OUT",
+            Self::ScannerBlocked(_) => "SCANNER_BLOCKED",
+            Self::QuotaExceeded { .. } => "QUOTA_EXCEEDED",
+            Self::InvalidContact(_) => "INVALID_CONTACT",
+        }
+    }
 }
 
 impl From<ServiceError> for Status {
     fn from(err: ServiceError) -> Self {
-        match err {
+        let reason = err.reason();
+        let dependency = match &err {
+            ServiceError::DependencyUnavailable { dependency, .. } | ServiceError::DependencyTimeout { dependency, .. } => Some(*dependency),
+            _ => None,
+        };
+        let retry_after = match &err {
+            ServiceError::RateLimited { retry_after, .. } | ServiceError::LockedOut { retry_after, .. } => {
+                Some(retry_after.as_secs_f64().ceil().max(1.0) as u64)
+            }
+            _ => None,
+        };
+        let rate_limit_scope = match &err {
+            ServiceError::RateLimited { scope, .. } => Some(scope.as_str()),
+            _ => None,
+        };
+        let quota_scope = match &err {
+            ServiceError::QuotaExceeded { scope } => Some(scope.as_str()),
+            _ => None,
+        };
+        let contact_reason = match &err {
+            ServiceError::InvalidContact(rejection) => Some(rejection.as_str()),
+            _ => None,
+        };
+        let mut status = match err {
             ServiceError::GrpcStatus(s) => s,
             ServiceError::RedisError(e) => Status::internal(format!("Redis hatası: {}", e)),
             ServiceError::GrpcTransportError(e) => Status::unavailable(format!("gRPC bağlantı hatası: {}", e)),
+            // Kullanıcıya yönelik mesajlar, kullanıcı adı tahminine ipucu vermemek için aynı tutulur.
+            ServiceError::UserNotFound(_) => Status::not_found("Invalid credentials"),
+            ServiceError::CredentialsRejected(_) => Status::permission_denied("Invalid credentials"),
+            ServiceError::DependencyUnavailable { dependency, .. } => Status::unavailable(format!("{} unavailable", dependency)),
+            ServiceError::DependencyTimeout { dependency, .. } => Status::deadline_exceeded(format!("{} timed out", dependency)),
+            ServiceError::RateLimited { .. } => Status::resource_exhausted("Rate limit exceeded"),
+            ServiceError::LockedOut { .. } => Status::permission_denied("Too many failed attempts"),
+            ServiceError::ScannerBlocked(_) => Status::permission_denied("Forbidden"),
+            ServiceError::QuotaExceeded { .. } => Status::resource_exhausted("Device limit reached"),
+            ServiceError::InvalidContact(rejection) => Status::invalid_argument(format!("Invalid contact: {}", rejection)),
             // Hata çözümü: Debug formatı kullanılarak Display gereksinimi geçici olarak karşılandı.
-            _ => Status::internal(format!("{:#?}", err)), 
+            _ => Status::internal(format!("{:#?}", err)),
+        };
+        let metadata = status.metadata_mut();
+        metadata.insert(ERROR_REASON_HEADER, MetadataValue::from_static(reason));
+        if let Some(dependency) = dependency {
+            metadata.insert(ERROR_DEPENDENCY_HEADER, MetadataValue::from_static(dependency));
+        }
+        if let Some(scope) = rate_limit_scope {
+            metadata.insert(RATE_LIMIT_SCOPE_HEADER, MetadataValue::from_static(scope));
+        }
+        if let Some(scope) = quota_scope {
+            metadata.insert(QUOTA_SCOPE_HEADER, MetadataValue::from_static(scope));
         }
+        if let Some(rejection) = contact_reason {
+            metadata.insert(CONTACT_REASON_HEADER, MetadataValue::from_static(rejection));
+        }
+        if let Some(secs) = retry_after {
+            metadata.insert(RETRY_AFTER_HEADER, MetadataValue::from(secs));
+        }
+        status
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,99 @@
+// src/grpc/acl.rs
+use crate::config::{AppConfig, SharedConfig, ACL_METHODS};
+use crate::metrics;
+use crate::tls::PeerCert;
+use hyper::{Request, Response};
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tonic::body::BoxBody;
+use tonic::transport::server::{TcpConnectInfo, TlsConnectInfo};
+use tonic::Status;
+use tower::{Layer, Service};
+use tracing::{debug, warn};
+
+/// mTLS istemci kimliğini (sertifika SAN/CN) `grpc_acl` ile karşılaştıran katman.
+/// ACL her çağrıda güncel yapılandırmadan okunur; reload ile değişiklikler hemen uygulanır.
+#[derive(Clone)]
+pub struct AclLayer {
+    config: SharedConfig,
+}
+
+impl AclLayer {
+    pub fn new(config: SharedConfig) -> Self {
+        Self { config }
+    }
+}
+
+impl<S> Layer<S> for AclLayer {
+    type Service = AclService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        AclService { inner, config: self.config.clone() }
+    }
+}
+
+#[derive(Clone)]
+pub struct AclService<S> {
+    inner: S,
+    config: SharedConfig,
+}
+
+impl<S, B> Service<Request<B>> for AclService<S>
+where
+    S: Service<Request<B>, Response = Response<BoxBody>> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+    B: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request<B>) -> Self::Future {
+        if let Err(status) = authorize(&self.config.load(), &req) {
+            return Box::pin(async move { Ok(status.to_http()) });
+        }
+        // poll_ready çağrılmış örnek kullanılır, yerine klonu bırakılır.
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        Box::pin(inner.call(req))
+    }
+}
+
+fn authorize<B>(config: &AppConfig, req: &Request<B>) -> Result<(), Status> {
+    if config.grpc_acl.is_empty() {
+        return Ok(());
+    }
+
+    let method = req.uri().path().rsplit('/').next().unwrap_or_default();
+    let conn = req.extensions().get::<TlsConnectInfo<TcpConnectInfo>>();
+    let identities = conn
+        .and_then(|c| c.peer_certs())
+        .and_then(|certs| certs.first().and_then(|cert| PeerCert::parse(cert.get_ref())))
+        .map(|cert| cert.identities)
+        .unwrap_or_default();
+
+    let allowed = config.grpc_acl.get(method).is_some_and(|allowed| {
+        allowed.iter().any(|a| (a == "*" && !identities.is_empty()) || identities.contains(a))
+    });
+    if allowed {
+        debug!(event="GRPC_ACL_ALLOW", rpc.method=%method, peer.identities=?identities, "RPC çağrısına izin verildi");
+        return Ok(());
+    }
+
+    let label = ACL_METHODS.iter().find(|m| **m == method).copied().unwrap_or("unknown");
+    metrics::inc_counter("registrar_grpc_acl_denied_total", &[("method", label)]);
+    warn!(
+        event = "GRPC_ACL_DENY",
+        audit = true,
+        rpc.method = %method,
+        peer.identities = ?identities,
+        peer.addr = ?conn.and_then(|c| c.get_ref().remote_addr()),
+        "RPC çağrısı ACL tarafından reddedildi"
+    );
+    Err(Status::permission_denied(format!("Caller is not allowed to invoke {}", method)))
+}
@@ -0,0 +1,257 @@
+// src/grpc/balancer.rs
+use crate::metrics;
+use arc_swap::ArcSwap;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU32, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tonic::transport::Channel;
+use tracing::{info, warn};
+
+/// Hedef adresten (URL) kanal üretir; TLS/plaintext ayrımı burada yapılır.
+pub type ChannelFactory = Arc<dyn Fn(&str) -> anyhow::Result<Channel> + Send + Sync>;
+
+/// Havuzdaki tek bir uç nokta ve sağlık durumu.
+pub struct Slot {
+    pub target: String,
+    pub channel: Channel,
+    consecutive_failures: AtomicU32,
+    /// Havuzun `epoch`'undan itibaren ms; 0 = devrede.
+    ejected_until_ms: AtomicU64,
+}
+
+/// Birden çok uç nokta arasında round-robin dağıtım yapan, ardışık geçici hatalardan
+/// sonra uç noktayı bir süre devre dışı bırakan (ejection) istemci tarafı havuz.
+pub struct EndpointPool {
+    name: &'static str,
+    eject_after: u32,
+    eject_for: Duration,
+    epoch: Instant,
+    next: AtomicUsize,
+    slots: ArcSwap<Vec<Arc<Slot>>>,
+    // Hedef listesi ve kanal üreticisi yalnızca güncellemelerde kullanılır.
+    update: Mutex<(Vec<String>, ChannelFactory)>,
+}
+
+impl EndpointPool {
+    pub fn new(name: &'static str, targets: Vec<String>, factory: ChannelFactory, eject_after: u32, eject_for: Duration) -> anyhow::Result<Self> {
+        let pool = Self {
+            name,
+            eject_after,
+            eject_for,
+            epoch: Instant::now(),
+            next: AtomicUsize::new(0),
+            slots: ArcSwap::from_pointee(Vec::new()),
+            update: Mutex::new((Vec::new(), factory)),
+        };
+        pool.set_targets(targets)?;
+        Ok(pool)
+    }
+
+    /// Hedef listesini günceller; değişmeyen hedeflerin kanalı ve sağlık durumu korunur.
+    pub fn set_targets(&self, mut targets: Vec<String>) -> anyhow::Result<()> {
+        targets.sort();
+        targets.dedup();
+        let mut update = self.update.lock().unwrap_or_else(|e| e.into_inner());
+        if update.0 == targets {
+            return Ok(());
+        }
+        let current = self.slots.load_full();
+        let mut slots = Vec::with_capacity(targets.len());
+        for target in &targets {
+            match current.iter().find(|s| &s.target == target) {
+                Some(slot) => slots.push(slot.clone()),
+                None => slots.push(Arc::new(Slot::new(target.clone(), (update.1)(target)?))),
+            }
+        }
+        info!(event="UPSTREAM_ENDPOINTS_UPDATED", target=self.name, endpoints=?targets, "Uç nokta listesi güncellendi");
+        self.slots.store(Arc::new(slots));
+        update.0 = targets;
+        self.record_gauges();
+        Ok(())
+    }
+
+    /// Kanal üreticisini değiştirir (ör. sertifika rotasyonu) ve tüm kanalları yeniden kurar.
+    pub fn set_factory(&self, factory: ChannelFactory) -> anyhow::Result<()> {
+        let mut update = self.update.lock().unwrap_or_else(|e| e.into_inner());
+        let slots = update.0.iter()
+            .map(|target| Ok(Arc::new(Slot::new(target.clone(), factory(target)?))))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        self.slots.store(Arc::new(slots));
+        update.1 = factory;
+        self.record_gauges();
+        Ok(())
+    }
+
+    /// Sıradaki sağlıklı uç noktayı seçer. Hepsi devre dışıysa, en erken geri dönecek
+    /// olan denenir (tüm havuzu kapatmak yerine).
+    pub fn pick(&self) -> Option<Arc<Slot>> {
+        let slots = self.slots.load();
+        if slots.is_empty() {
+            return None;
+        }
+        let now = self.now_ms();
+        let start = self.next.fetch_add(1, Ordering::Relaxed);
+        (0..slots.len())
+            .map(|i| &slots[(start + i) % slots.len()])
+            .find(|slot| slot.ejected_until_ms.load(Ordering::Relaxed) <= now)
+            .or_else(|| slots.iter().min_by_key(|slot| slot.ejected_until_ms.load(Ordering::Relaxed)))
+            .cloned()
+    }
+
+    pub fn report_success(&self, slot: &Slot) {
+        slot.consecutive_failures.store(0, Ordering::Relaxed);
+        if slot.ejected_until_ms.swap(0, Ordering::Relaxed) != 0 {
+            info!(event="UPSTREAM_ENDPOINT_RESTORED", target=self.name, endpoint=%slot.target, "Uç nokta yeniden devrede");
+            self.record_gauges();
+        }
+    }
+
+    /// Geçici hata bildirir; eşik aşılınca uç nokta `eject_for` süresince devre dışı kalır.
+    pub fn report_failure(&self, slot: &Slot) {
+        let failures = slot.consecutive_failures.fetch_add(1, Ordering::Relaxed) + 1;
+        if failures < self.eject_after {
+            return;
+        }
+        slot.consecutive_failures.store(0, Ordering::Relaxed);
+        slot.ejected_until_ms.store(self.now_ms() + self.eject_for.as_millis() as u64, Ordering::Relaxed);
+        metrics::inc_counter("registrar_upstream_ejections_total", &[("target", self.name)]);
+        warn!(
+            event = "UPSTREAM_ENDPOINT_EJECTED",
+            target = self.name,
+            endpoint = %slot.target,
+            failures = failures,
+            eject_secs = self.eject_for.as_secs(),
+            "Uç nokta ardışık hatalar nedeniyle devre dışı bırakıldı"
+        );
+        self.record_gauges();
+    }
+
+    fn now_ms(&self) -> u64 {
+        // 0 "devrede" anlamına geldiği için zaman 1'den başlar.
+        self.epoch.elapsed().as_millis() as u64 + 1
+    }
+
+    fn record_gauges(&self) {
+        let now = self.now_ms();
+        let slots = self.slots.load();
+        let ejected = slots.iter().filter(|s| s.ejected_until_ms.load(Ordering::Relaxed) > now).count();
+        metrics::set_gauge("registrar_upstream_endpoints", &[("target", self.name), ("state", "healthy")], (slots.len() - ejected) as f64);
+        metrics::set_gauge("registrar_upstream_endpoints", &[("target", self.name), ("state", "ejected")], ejected as f64);
+    }
+}
+
+impl Slot {
+    fn new(target: String, channel: Channel) -> Self {
+        Self { target, channel, consecutive_failures: AtomicU32::new(0), ejected_until_ms: AtomicU64::new(0) }
+    }
+}
+
+/// `scheme://host:port` adresindeki host'u A/AAAA kayıtlarına çözer ve her IP için
+/// aynı şema ve portla bir hedef döner.
+pub async fn resolve_targets(url: &str) -> anyhow::Result<Vec<String>> {
+    let uri: hyper::Uri = url.parse()?;
+    let host = uri.host().ok_or_else(|| anyhow::anyhow!("URL'de host yok: {}", url))?;
+    let port = uri.port_u16()
+        .or_else(|| match uri.scheme_str() {
+            Some("http") => Some(80),
+            Some("https") => Some(443),
+            _ => None,
+        })
+        .ok_or_else(|| anyhow::anyhow!("URL'de port yok: {}", url))?;
+    let addrs: Vec<SocketAddr> = tokio::net::lookup_host((host, port)).await?.collect();
+    if addrs.is_empty() {
+        anyhow::bail!("{} için DNS kaydı bulunamadı", host);
+    }
+    let scheme = uri.scheme_str().map(|s| format!("{}://", s)).unwrap_or_default();
+    Ok(addrs.into_iter().map(|addr| format!("{}{}", scheme, addr)).collect())
+}
+
+/// DNS kayıtlarını periyodik olarak çözüp havuzu günceller. Çözümleme başarısızsa
+/// mevcut uç noktalar korunur.
+pub fn spawn_dns_refresh(pool: Arc<EndpointPool>, url: String, every: Duration) {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(every);
+        loop {
+            ticker.tick().await;
+            match resolve_targets(&url).await {
+                Ok(targets) => {
+                    if let Err(e) = pool.set_targets(targets) {
+                        warn!(event="UPSTREAM_DNS_UPDATE_FAIL", url=%url, error=%e, "Uç nokta listesi güncellenemedi");
+                    }
+                }
+                Err(e) => warn!(event="UPSTREAM_DNS_RESOLVE_FAIL", url=%url, error=%e, "DNS çözümlenemedi, mevcut uç noktalar korunuyor"),
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tonic::transport::Endpoint;
+
+    fn pool(targets: &[&str], eject_for: Duration) -> EndpointPool {
+        let factory: ChannelFactory = Arc::new(|target: &str| Ok(Endpoint::from_shared(target.to_string())?.connect_lazy()));
+        EndpointPool::new("test", targets.iter().map(|t| t.to_string()).collect(), factory, 2, eject_for).unwrap()
+    }
+
+    fn picks(pool: &EndpointPool, n: usize) -> Vec<String> {
+        (0..n).map(|_| pool.pick().unwrap().target.clone()).collect()
+    }
+
+    #[tokio::test]
+    async fn picks_endpoints_round_robin() {
+        let pool = pool(&["http://10.0.0.2:1", "http://10.0.0.1:1", "http://10.0.0.1:1"], Duration::from_secs(60));
+        // Hedefler sıralanır ve tekilleştirilir.
+        assert_eq!(picks(&pool, 4), ["http://10.0.0.1:1", "http://10.0.0.2:1", "http://10.0.0.1:1", "http://10.0.0.2:1"]);
+    }
+
+    #[tokio::test]
+    async fn ejects_after_failures_and_readmits_after_cooldown() {
+        let pool = pool(&["http://10.0.0.1:1", "http://10.0.0.2:1"], Duration::from_millis(50));
+        let bad = pool.slots.load()[0].clone();
+
+        pool.report_failure(&bad);
+        assert!(picks(&pool, 4).contains(&bad.target), "eşik altında uç nokta devrede kalır");
+        pool.report_failure(&bad);
+        assert!(picks(&pool, 4).iter().all(|t| t != &bad.target));
+
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        assert!(picks(&pool, 4).contains(&bad.target));
+        pool.report_success(&bad);
+        assert_eq!(bad.ejected_until_ms.load(Ordering::Relaxed), 0);
+    }
+
+    #[tokio::test]
+    async fn all_ejected_falls_back_to_earliest_return() {
+        let pool = pool(&["http://10.0.0.1:1", "http://10.0.0.2:1"], Duration::from_secs(60));
+        let slots = pool.slots.load_full();
+        (0..2).for_each(|_| pool.report_failure(&slots[1]));
+        tokio::time::sleep(Duration::from_millis(5)).await;
+        (0..2).for_each(|_| pool.report_failure(&slots[0]));
+
+        assert_eq!(picks(&pool, 3), vec![slots[1].target.clone(); 3]);
+    }
+
+    #[tokio::test]
+    async fn target_updates_keep_health_of_surviving_endpoints() {
+        let pool = pool(&["http://10.0.0.1:1", "http://10.0.0.2:1"], Duration::from_secs(60));
+        let kept = pool.slots.load()[0].clone();
+        (0..2).for_each(|_| pool.report_failure(&kept));
+
+        pool.set_targets(vec!["http://10.0.0.3:1".to_string(), kept.target.clone()]).unwrap();
+        let slots = pool.slots.load();
+        assert_eq!(slots.len(), 2);
+        assert!(Arc::ptr_eq(&slots[0], &kept));
+        assert!(picks(&pool, 4).iter().all(|t| t == "http://10.0.0.3:1"));
+    }
+
+    #[tokio::test]
+    async fn resolves_ip_literal_with_scheme_and_default_port() {
+        assert_eq!(resolve_targets("http://127.0.0.1:50051").await.unwrap(), ["http://127.0.0.1:50051"]);
+        assert_eq!(resolve_targets("https://127.0.0.1").await.unwrap(), ["https://127.0.0.1:443"]);
+        // Bilinmeyen şemada port tahmin edilmez.
+        assert!(resolve_targets("grpc://127.0.0.1").await.is_err());
+    }
+}
@@ -1,28 +1,145 @@
 // sentiric-registrar-service/src/grpc/client.rs
 
 use crate::config::AppConfig;
+use crate::grpc::balancer::{self, ChannelFactory, EndpointPool};
+use crate::grpc::deadline::Deadline;
+use crate::grpc::resilience::{self, CircuitBreaker, RetryPolicy};
+use crate::metrics;
+use crate::telemetry;
+use crate::tls::TlsState;
 use anyhow::Result;
 use sentiric_contracts::sentiric::user::v1::user_service_client::UserServiceClient;
-use tonic::transport::{Channel, ClientTlsConfig, Certificate, Identity};
+use sentiric_contracts::sentiric::user::v1::{GetSipCredentialsRequest, GetSipCredentialsResponse};
+use std::sync::Arc;
+use tonic::transport::{Channel, Endpoint};
+use tonic::{Request, Status};
 use std::time::Duration;
 use tracing::{info, warn};
 
 pub struct InternalClients {
-    pub user: UserServiceClient<Channel>,
+    pub user: UserClient,
 }
 
 impl InternalClients {
-    pub async fn connect(config: &AppConfig) -> Result<Self> {
-        info!("User Service'e bağlanılıyor...");
-        let user_channel = create_secure_channel(&config.user_service_url, "user-service", config).await?;
-        
+    /// Kanal tembel kurulur: user-service ayakta olmasa da servis başlar, bağlantı
+    /// ilk çağrıda kurulur ve kopunca tonic tarafından yeniden denenir.
+    /// `tls` yoksa (plaintext geliştirme profili) kanal şifresiz kurulur.
+    pub fn connect(config: &AppConfig, tls: Option<&TlsState>) -> Result<Self> {
+        let breaker = Arc::new(CircuitBreaker::new(
+            "user-service",
+            config.user_service_breaker_threshold,
+            Duration::from_secs(config.user_service_breaker_open_secs),
+        ));
+        let retry = RetryPolicy {
+            max_attempts: config.user_service_retry_attempts,
+            base_delay: Duration::from_millis(config.user_service_retry_base_ms),
+            max_delay: Duration::from_secs(1),
+        };
+        let pool = Arc::new(EndpointPool::new(
+            "user-service",
+            config.user_service_targets(),
+            channel_factory(tls),
+            config.user_service_eject_after_failures,
+            Duration::from_secs(config.user_service_eject_secs),
+        )?);
+        // Tek bir DNS adı verilmişse A/AAAA kayıtları periyodik çözülür, her IP ayrı uç noktadır.
+        if config.user_service_dns_refresh_secs > 0 && config.user_service_urls.is_empty() {
+            balancer::spawn_dns_refresh(pool.clone(), config.user_service_url.clone(), Duration::from_secs(config.user_service_dns_refresh_secs));
+        }
         Ok(Self {
-            user: UserServiceClient::new(user_channel),
+            user: UserClient { pool, breaker, retry },
         })
     }
+
+    /// Kanalları (ör. sertifika rotasyonunda) yeniden kurar; devre kesici ve uç nokta listesi korunur.
+    pub fn reconnect(&self, tls: Option<&TlsState>) -> Result<()> {
+        self.user.pool.set_factory(channel_factory(tls))
+    }
+}
+
+fn channel_factory(tls: Option<&TlsState>) -> ChannelFactory {
+    match tls.cloned() {
+        Some(tls) => Arc::new(move |url: &str| create_secure_channel(url, "user-service", &tls)),
+        None => Arc::new(create_plaintext_channel),
+    }
+}
+
+/// Yeniden deneme ve devre kesici ile sarılmış User Service istemcisi.
+#[derive(Clone)]
+pub struct UserClient {
+    pool: Arc<EndpointPool>,
+    breaker: Arc<CircuitBreaker>,
+    retry: RetryPolicy,
+}
+
+impl UserClient {
+    /// Geçici hatalarda (UNAVAILABLE, DEADLINE_EXCEEDED, ABORTED) jitter'lı sınırlı
+    /// yeniden deneme yapar; her deneme havuzdan sıradaki sağlıklı uç noktaya gider.
+    /// Devre açıksa çağrı yapılmadan UNAVAILABLE döner. Her deneme `attempt_timeout` ile
+    /// kalan RPC bütçesinin küçüğüyle sınırlanır ve bu süre `grpc-timeout` olarak iletilir.
+    pub async fn get_sip_credentials(&self, req: GetSipCredentialsRequest, deadline: &Deadline, attempt_timeout: Duration) -> Result<GetSipCredentialsResponse, Status> {
+        let mut attempt = 1;
+        loop {
+            if deadline.is_expired() {
+                metrics::inc_counter("registrar_user_service_requests_total", &[("result", "deadline_exceeded")]);
+                return Err(Status::deadline_exceeded("request deadline exceeded before user-service call"));
+            }
+            // İzin düşerse (erken dönüş, iptal) half-open deneme hata sayılır; devre takılı kalmaz.
+            let Some(permit) = self.breaker.allow() else {
+                metrics::inc_counter("registrar_user_service_requests_total", &[("result", "circuit_open")]);
+                return Err(Status::unavailable("user-service circuit breaker open"));
+            };
+
+            let Some(slot) = self.pool.pick() else {
+                return Err(Status::unavailable("no user-service endpoints"));
+            };
+
+            // Trace bağlamı (traceparent/tracestate) her denemede User Service'e taşınır.
+            let budget = deadline.budget(attempt_timeout);
+            let mut request = Request::new(req.clone());
+            request.set_timeout(budget);
+            telemetry::inject_current_context(request.metadata_mut());
+
+            let call = UserServiceClient::new(slot.channel.clone()).get_sip_credentials(request);
+            let result = match tokio::time::timeout(budget, call).await {
+                Ok(result) => result,
+                Err(_) => Err(Status::deadline_exceeded(format!("user-service did not respond within {}ms", budget.as_millis()))),
+            };
+
+            match result {
+                Ok(res) => {
+                    self.pool.report_success(&slot);
+                    permit.success();
+                    metrics::inc_counter("registrar_user_service_requests_total", &[("result", "ok")]);
+                    return Ok(res.into_inner());
+                }
+                Err(status) if resilience::is_transient(status.code()) => {
+                    self.pool.report_failure(&slot);
+                    permit.failure();
+                    let delay = self.retry.backoff(attempt);
+                    // Bir sonraki deneme için bütçe kalmadıysa beklemeden dön.
+                    if attempt >= self.retry.max_attempts || delay >= deadline.remaining() {
+                        metrics::inc_counter("registrar_user_service_requests_total", &[("result", "transient_error")]);
+                        return Err(status);
+                    }
+                    metrics::inc_counter("registrar_user_service_retries_total", &[]);
+                    warn!(event="USER_SERVICE_RETRY", attempt=attempt, endpoint=%slot.target, delay_ms=delay.as_millis() as u64, code=?status.code(), "User Service geçici hata, yeniden deneniyor");
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                }
+                Err(status) => {
+                    // Kalıcı yanıtlar (NOT_FOUND vb.) servisin ayakta olduğunu gösterir.
+                    self.pool.report_success(&slot);
+                    permit.success();
+                    metrics::inc_counter("registrar_user_service_requests_total", &[("result", "error")]);
+                    return Err(status);
+                }
+            }
+        }
+    }
 }
 
-async fn create_secure_channel(url: &str, server_name: &str, config: &AppConfig) -> Result<Channel> {
+fn create_secure_channel(url: &str, server_name: &str, tls: &TlsState) -> Result<Channel> {
     let target_url = if url.starts_with("https://") {
         url.to_string()
     } else if url.starts_with("http://") {
@@ -32,29 +149,36 @@ async fn create_secure_channel(url: &str, server_name: &str, config: &AppConfig)
         format!("https://{}", url)
     };
 
-    let cert = tokio::fs::read(&config.cert_path).await?;
-    let key = tokio::fs::read(&config.key_path).await?;
-    let identity = Identity::from_pem(cert, key);
-    let ca_cert = tokio::fs::read(&config.ca_path).await?;
-    let ca_certificate = Certificate::from_pem(ca_cert);
+    // Sertifikalar diskten değil, rotasyonla güncellenen TLS durumundan alınır.
+    let tls_config = tls.material().client_tls_config(server_name);
 
-    let tls_config = ClientTlsConfig::new()
-        .domain_name(server_name)
-        .ca_certificate(ca_certificate)
-        .identity(identity);
+    info!(url=%target_url, server_name=%server_name, "Güvenli gRPC kanalı hazırlandı (ilk çağrıda bağlanacak).");
 
-    info!(url=%target_url, server_name=%server_name, "Güvenli gRPC kanalına bağlanılıyor...");
+    let channel = endpoint(target_url)?
+        .tls_config(tls_config)?
+        .connect_lazy();
+    Ok(channel)
+}
 
+fn create_plaintext_channel(url: &str) -> Result<Channel> {
+    let target_url = if url.starts_with("http://") {
+        url.to_string()
+    } else if let Some(rest) = url.strip_prefix("https://") {
+        warn!(url, "Plaintext profilde https şeması http'ye çevriliyor.");
+        format!("http://{}", rest)
+    } else {
+        format!("http://{}", url)
+    };
+
+    warn!(event="GRPC_PLAINTEXT_CLIENT", url=%target_url, "⚠️ User Service'e ŞİFRESİZ gRPC ile bağlanılıyor (yalnızca geliştirme).");
+    Ok(endpoint(target_url)?.connect_lazy())
+}
+
+fn endpoint(target_url: String) -> Result<Endpoint> {
     // [KRİTİK DÜZELTME]: HTTP/2 Keep-Alive eklendi.
-    let channel = Channel::from_shared(target_url)?
+    Ok(Channel::from_shared(target_url)?
         .connect_timeout(Duration::from_secs(5))
         .keep_alive_while_idle(true)
         .http2_keep_alive_interval(Duration::from_secs(15))
-        .keep_alive_timeout(Duration::from_secs(5))
-        .tls_config(tls_config)?
-        .connect()
-        .await?;
-
-    info!("gRPC bağlantısı başarılı.");
-    Ok(channel)
+        .keep_alive_timeout(Duration::from_secs(5)))
 }
\ No newline at end of file
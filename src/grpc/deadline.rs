@@ -0,0 +1,118 @@
+// src/grpc/deadline.rs
+use crate::error::ServiceError;
+use std::future::Future;
+use std::time::{Duration, Instant};
+use tonic::metadata::MetadataMap;
+
+const GRPC_TIMEOUT_HEADER: &str = "grpc-timeout";
+
+/// Bir RPC'nin toplam süre bütçesi. Çağıranın `grpc-timeout` değerinden, yoksa
+/// yapılandırmadaki varsayılandan hesaplanır; alt çağrılar kalan bütçeyle sınırlanır.
+#[derive(Debug, Clone, Copy)]
+pub struct Deadline {
+    at: Instant,
+}
+
+impl Deadline {
+    pub fn from_metadata(metadata: &MetadataMap, default: Duration) -> Self {
+        let budget = metadata.get(GRPC_TIMEOUT_HEADER)
+            .and_then(|v| v.to_str().ok())
+            .and_then(parse_grpc_timeout)
+            .unwrap_or(default);
+        Self { at: Instant::now() + budget }
+    }
+
+    pub fn remaining(&self) -> Duration {
+        self.at.saturating_duration_since(Instant::now())
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.remaining().is_zero()
+    }
+
+    /// Alt çağrı için süre: kendi sınırı ile kalan bütçenin küçüğü.
+    pub fn budget(&self, limit: Duration) -> Duration {
+        self.remaining().min(limit)
+    }
+
+    /// `fut`'u `budget(limit)` süresiyle çalıştırır; süre aşılırsa `dependency` için
+    /// `DependencyTimeout` döner.
+    pub async fn run<T, F>(&self, limit: Duration, dependency: &'static str, fut: F) -> Result<T, ServiceError>
+    where
+        F: Future<Output = T>,
+    {
+        let budget = self.budget(limit);
+        tokio::time::timeout(budget, fut).await.map_err(|_| ServiceError::DependencyTimeout {
+            dependency,
+            reason: format!("{}ms içinde yanıt yok", budget.as_millis()),
+        })
+    }
+}
+
+/// gRPC `grpc-timeout` değerini ayrıştırır: en fazla 8 hane + birim (H, M, S, m, u, n).
+pub fn parse_grpc_timeout(raw: &str) -> Option<Duration> {
+    let raw = raw.trim();
+    if raw.len() < 2 || raw.len() > 9 || !raw.is_ascii() {
+        return None;
+    }
+    let (digits, unit) = raw.split_at(raw.len() - 1);
+    // `u64::from_str` baştaki `+` işaretini kabul eder; gRPC yalnızca rakam tanımlar.
+    if !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let value: u64 = digits.parse().ok()?;
+    match unit {
+        "H" => Some(Duration::from_secs(value * 3600)),
+        "M" => Some(Duration::from_secs(value * 60)),
+        "S" => Some(Duration::from_secs(value)),
+        "m" => Some(Duration::from_millis(value)),
+        "u" => Some(Duration::from_micros(value)),
+        "n" => Some(Duration::from_nanos(value)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_every_grpc_timeout_unit() {
+        assert_eq!(parse_grpc_timeout("2H"), Some(Duration::from_secs(7200)));
+        assert_eq!(parse_grpc_timeout("3M"), Some(Duration::from_secs(180)));
+        assert_eq!(parse_grpc_timeout("5S"), Some(Duration::from_secs(5)));
+        assert_eq!(parse_grpc_timeout("250m"), Some(Duration::from_millis(250)));
+        assert_eq!(parse_grpc_timeout("1500u"), Some(Duration::from_micros(1500)));
+        assert_eq!(parse_grpc_timeout("99999999n"), Some(Duration::from_nanos(99_999_999)));
+        assert_eq!(parse_grpc_timeout(" 0S "), Some(Duration::ZERO));
+    }
+
+    #[test]
+    fn rejects_malformed_grpc_timeout() {
+        for raw in ["", "S", "5", "5s", "5ms", "123456789S", "+5S", "-5S", "5 S", "1.5S", "5é", "éS"] {
+            assert_eq!(parse_grpc_timeout(raw), None, "{:?}", raw);
+        }
+    }
+
+    #[test]
+    fn caller_timeout_overrides_default_budget() {
+        let mut metadata = MetadataMap::new();
+        metadata.insert(GRPC_TIMEOUT_HEADER, "100m".parse().unwrap());
+        let deadline = Deadline::from_metadata(&metadata, Duration::from_secs(30));
+        assert!(deadline.remaining() <= Duration::from_millis(100));
+        assert_eq!(deadline.budget(Duration::from_millis(10)), Duration::from_millis(10));
+
+        let fallback = Deadline::from_metadata(&MetadataMap::new(), Duration::from_secs(30));
+        assert!(fallback.remaining() > Duration::from_secs(29));
+        assert!(!fallback.is_expired());
+    }
+
+    #[test]
+    fn expired_budget_is_zero() {
+        let mut metadata = MetadataMap::new();
+        metadata.insert(GRPC_TIMEOUT_HEADER, "0n".parse().unwrap());
+        let deadline = Deadline::from_metadata(&metadata, Duration::from_secs(30));
+        assert!(deadline.is_expired());
+        assert_eq!(deadline.budget(Duration::from_secs(1)), Duration::ZERO);
+    }
+}
@@ -1,3 +1,7 @@
 // sentiric-registrar-service/src/grpc/mod.rs
+pub mod acl;
+pub mod balancer;
 pub mod service;
-pub mod client;
\ No newline at end of file
+pub mod client;
+pub mod deadline;
+pub mod resilience;
\ No newline at end of file
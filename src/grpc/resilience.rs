@@ -0,0 +1,224 @@
+// src/grpc/resilience.rs
+use crate::metrics;
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tonic::Code;
+use tracing::{info, warn};
+
+/// Yeniden denemeye ve devre kesiciye sayılan (geçici) hata kodları.
+pub fn is_transient(code: Code) -> bool {
+    matches!(code, Code::Unavailable | Code::DeadlineExceeded | Code::Aborted)
+}
+
+/// Üstel geri çekilme + tam jitter ile sınırlı yeniden deneme.
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    /// İlk deneme dahil toplam deneme sayısı.
+    pub max_attempts: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl RetryPolicy {
+    /// `attempt` (1'den başlar) numaralı denemeden sonra beklenecek süre: [0, min(max, base * 2^(attempt-1))).
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let ceiling = self.base_delay
+            .saturating_mul(1u32 << attempt.saturating_sub(1).min(16))
+            .min(self.max_delay);
+        let ceiling_ms = ceiling.as_millis().max(1) as u64;
+        Duration::from_millis(random_u64() % ceiling_ms)
+    }
+}
+
+/// Harici bir rastgele sayı bağımlılığı olmadan jitter için yeterli entropi.
+pub(crate) fn random_u64() -> u64 {
+    RandomState::new().build_hasher().finish()
+}
+
+#[derive(Debug, Default)]
+struct BreakerState {
+    open_until: Option<Instant>,
+    probing: bool,
+}
+
+/// Ardışık geçici hatalardan sonra açılan devre kesici. Açıkken çağrılar hemen
+/// reddedilir; süre dolunca tek bir deneme çağrısına (half-open) izin verilir.
+/// Kapalı durumdaki sıcak yol yalnızca atomik okuma yapar.
+#[derive(Debug)]
+pub struct CircuitBreaker {
+    name: &'static str,
+    failure_threshold: u32,
+    open_duration: Duration,
+    consecutive_failures: AtomicU32,
+    open: AtomicBool,
+    state: Mutex<BreakerState>,
+}
+
+impl CircuitBreaker {
+    pub fn new(name: &'static str, failure_threshold: u32, open_duration: Duration) -> Self {
+        metrics::set_gauge("registrar_circuit_breaker_open", &[("target", name)], 0.0);
+        Self {
+            name,
+            failure_threshold,
+            open_duration,
+            consecutive_failures: AtomicU32::new(0),
+            open: AtomicBool::new(false),
+            state: Mutex::new(BreakerState::default()),
+        }
+    }
+
+    /// Çağrı yapılabilir mi? Half-open durumda yalnızca bir deneme çağrısına izin verir.
+    /// Dönen izin sonuçla (`success`/`failure`) kapatılmalıdır; kapatılmadan düşen bir
+    /// deneme izni (erken dönüş, iptal edilen future) hata sayılır ve devre yeniden açılır.
+    pub fn allow(&self) -> Option<BreakerPermit<'_>> {
+        if !self.open.load(Ordering::Acquire) {
+            return Some(BreakerPermit { breaker: self, probe: false });
+        }
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        match state.open_until {
+            None => Some(BreakerPermit { breaker: self, probe: false }),
+            Some(until) if Instant::now() < until => None,
+            Some(_) if state.probing => None,
+            Some(_) => {
+                state.probing = true;
+                Some(BreakerPermit { breaker: self, probe: true })
+            }
+        }
+    }
+
+    fn on_success(&self) {
+        self.consecutive_failures.store(0, Ordering::Relaxed);
+        if !self.open.load(Ordering::Acquire) {
+            return;
+        }
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        if state.open_until.take().is_some() {
+            self.open.store(false, Ordering::Release);
+            metrics::set_gauge("registrar_circuit_breaker_open", &[("target", self.name)], 0.0);
+            info!(event="CIRCUIT_BREAKER_CLOSED", target=self.name, "Devre kesici kapandı, çağrılar normale döndü");
+        }
+        state.probing = false;
+    }
+
+    fn on_failure(&self) {
+        let failures = self.consecutive_failures.fetch_add(1, Ordering::Relaxed) + 1;
+        if !self.open.load(Ordering::Acquire) && failures < self.failure_threshold {
+            return;
+        }
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        if state.probing || state.open_until.is_none() {
+            state.open_until = Some(Instant::now() + self.open_duration);
+            state.probing = false;
+            self.open.store(true, Ordering::Release);
+            metrics::set_gauge("registrar_circuit_breaker_open", &[("target", self.name)], 1.0);
+            metrics::inc_counter("registrar_circuit_breaker_trips_total", &[("target", self.name)]);
+            warn!(
+                event = "CIRCUIT_BREAKER_OPEN",
+                target = self.name,
+                failures = failures,
+                open_secs = self.open_duration.as_secs(),
+                "Ardışık hatalar nedeniyle devre kesici açıldı"
+            );
+        }
+    }
+}
+
+/// `CircuitBreaker::allow` ile alınan çağrı izni.
+#[must_use = "izin success() veya failure() ile kapatılmalı"]
+pub struct BreakerPermit<'a> {
+    breaker: &'a CircuitBreaker,
+    /// Half-open deneme çağrısı mı? Yalnızca deneme izinleri düşerken hata sayılır.
+    probe: bool,
+}
+
+impl BreakerPermit<'_> {
+    pub fn success(self) {
+        self.breaker.on_success();
+        std::mem::forget(self);
+    }
+
+    pub fn failure(self) {
+        self.breaker.on_failure();
+        std::mem::forget(self);
+    }
+}
+
+impl Drop for BreakerPermit<'_> {
+    fn drop(&mut self) {
+        if self.probe {
+            self.breaker.on_failure();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OPEN: Duration = Duration::from_millis(20);
+
+    fn tripped() -> CircuitBreaker {
+        let breaker = CircuitBreaker::new("test", 2, OPEN);
+        breaker.allow().unwrap().failure();
+        breaker.allow().unwrap().failure();
+        breaker
+    }
+
+    #[test]
+    fn opens_after_threshold_and_closes_after_successful_probe() {
+        let breaker = CircuitBreaker::new("test", 2, OPEN);
+        breaker.allow().unwrap().failure();
+        assert!(breaker.allow().is_some());
+        breaker.allow().unwrap().failure();
+        assert!(breaker.allow().is_none(), "eşikten sonra açık olmalı");
+
+        std::thread::sleep(OPEN);
+        let probe = breaker.allow().expect("half-open deneme izni");
+        assert!(breaker.allow().is_none(), "aynı anda tek deneme");
+        probe.success();
+
+        assert!(breaker.allow().is_some());
+        assert!(breaker.allow().is_some());
+    }
+
+    #[test]
+    fn failed_probe_reopens() {
+        let breaker = tripped();
+        std::thread::sleep(OPEN);
+        breaker.allow().unwrap().failure();
+        assert!(breaker.allow().is_none());
+        std::thread::sleep(OPEN);
+        assert!(breaker.allow().is_some());
+    }
+
+    #[test]
+    fn dropped_probe_does_not_leave_breaker_stuck() {
+        let breaker = tripped();
+        std::thread::sleep(OPEN);
+        drop(breaker.allow().expect("half-open deneme izni"));
+        assert!(breaker.allow().is_none(), "düşen deneme hata sayılır");
+
+        std::thread::sleep(OPEN);
+        breaker.allow().expect("yeni deneme izni").success();
+        assert!(breaker.allow().is_some());
+    }
+
+    #[test]
+    fn dropped_closed_permit_is_not_a_failure() {
+        let breaker = CircuitBreaker::new("test", 1, OPEN);
+        drop(breaker.allow().unwrap());
+        assert!(breaker.allow().is_some());
+    }
+
+    #[test]
+    fn backoff_stays_under_ceiling() {
+        let retry = RetryPolicy { max_attempts: 5, base_delay: Duration::from_millis(10), max_delay: Duration::from_millis(50) };
+        for attempt in 1..=8 {
+            assert!(retry.backoff(attempt) < Duration::from_millis(50));
+        }
+        assert!(retry.backoff(1) < Duration::from_millis(10));
+    }
+}
@@ -1,103 +1,585 @@
 // src/grpc/service.rs
 
 use std::sync::Arc;
-use tokio::sync::Mutex;
 use sentiric_contracts::sentiric::sip::v1::{
     registrar_service_server::RegistrarService, 
     RegisterRequest, RegisterResponse, 
     UnregisterRequest, UnregisterResponse, 
     LookupContactRequest, LookupContactResponse
 };
-use sentiric_contracts::sentiric::user::v1::GetSipCredentialsRequest;
+use sentiric_contracts::sentiric::user::v1::{GetSipCredentialsRequest, GetSipCredentialsResponse};
+use std::net::IpAddr;
+use std::time::Duration;
+use tokio::sync::Semaphore;
+use tonic::metadata::{MetadataMap, MetadataValue};
 use tonic::{Request, Response, Status};
-use tracing::{info, error, warn, instrument, Span};
+use tracing::{debug, info, error, warn, instrument, Span};
 use crate::grpc::client::InternalClients;
-use crate::data::store::RegistrationStore;
-use crate::config::AppConfig;
+use crate::grpc::deadline::Deadline;
+use crate::error::ServiceError;
+use crate::data::credential_cache::CredentialCache;
+use crate::data::lockout::{LockScope, LockoutRule, LockoutStore};
+use crate::data::rate_limit::{BucketLimit, LimitScope, RateLimiter};
+use crate::data::tenant_networks::TenantNetworks;
+use crate::data::store::{BindingInfo, RegisterOutcome, RegistrationStore};
+use crate::metrics;
+use crate::config::SharedConfig;
+use crate::contact;
+use crate::policy::{self, PolicyStore};
+use crate::telemetry;
+
+/// Proxy'nin doğrulanan Authorization başlığındaki digest algoritmasını ilettiği metadata.
+const DIGEST_ALGORITHM_HEADER: &str = "x-sip-digest-algorithm";
+/// REGISTER'ın Call-ID'si ve (Contact'ta yoksa) cihaz örneği (+sip.instance).
+const CALL_ID_HEADER: &str = "x-sip-call-id";
+const INSTANCE_HEADER: &str = "x-sip-instance";
+/// Proxy'nin gördüğü REGISTER kaynak adresi (IP, portsuz).
+const SOURCE_IP_HEADER: &str = "x-sip-source-ip";
+/// REGISTER'ın User-Agent başlığı.
+const USER_AGENT_HEADER: &str = "x-sip-user-agent";
+/// LookupContact yanıtında bağlamanın cihaz bilgisi.
+const DEVICE_USER_AGENT_HEADER: &str = "x-registrar-user-agent";
+const DEVICE_INSTANCE_HEADER: &str = "x-registrar-instance";
+
+fn metadata_str<T>(request: &Request<T>, key: &str) -> Option<String> {
+    request.metadata().get(key)
+        .and_then(|v| v.to_str().ok())
+        .map(str::trim)
+        .filter(|v| !v.is_empty())
+        .map(str::to_string)
+}
+
+/// REGISTER'ı kimlik doğrulamasından önce süzen Redis tabanlı korumalar.
+#[derive(Clone)]
+pub struct RegisterGuards {
+    pub rate_limiter: RateLimiter,
+    pub lockouts: LockoutStore,
+    pub networks: TenantNetworks,
+    /// Aynı anda bekletilen tarayıcı isteklerini sınırlar (`scanner_tarpit_max_concurrent`).
+    pub tarpit_slots: Arc<Semaphore>,
+}
 
 pub struct MyRegistrarService {
     store: RegistrationStore,
-    clients: Arc<Mutex<InternalClients>>,
-    config: Arc<AppConfig>,
+    clients: Arc<InternalClients>,
+    credential_cache: CredentialCache,
+    guards: RegisterGuards,
+    config: SharedConfig,
+    policies: PolicyStore,
 }
 
 impl MyRegistrarService {
-    pub fn new(store: RegistrationStore, clients: Arc<Mutex<InternalClients>>, config: Arc<AppConfig>) -> Self {
-        Self { store, clients, config }
+    pub fn new(store: RegistrationStore, clients: Arc<InternalClients>, credential_cache: CredentialCache, guards: RegisterGuards, config: SharedConfig, policies: PolicyStore) -> Self {
+        Self { store, clients, credential_cache, guards, config, policies }
+    }
+
+    /// Tenant'ın CIDR allow/deny listesini proxy'nin bildirdiği kaynak adrese uygular.
+    fn check_source_network(&self, tenant: &str, aor: &str, source_ip: Option<IpAddr>) -> Result<(), Status> {
+        let Some(networks) = self.guards.networks.get(tenant) else { return Ok(()) };
+        let Err(reason) = networks.check(source_ip) else { return Ok(()) };
+
+        metrics::inc_counter("registrar_source_network_rejected_total", &[("reason", reason)]);
+        warn!(
+            event = "SECURITY_SOURCE_NETWORK_REJECT",
+            audit = true,
+            tenant.id = %tenant,
+            aor = %aor,
+            source_ip = ?source_ip,
+            reason = reason,
+            "Kaynak adres tenant ağ listesine uymuyor"
+        );
+        let source = source_ip.map(|ip| ip.to_string()).unwrap_or_else(|| "-".to_string());
+        Err(policy::PolicyViolation::SourceNotAllowed(source).into())
+    }
+
+    /// Contact'ı saklamadan önce doğrular; geçerliyse saklanacak biçimini döner.
+    fn check_contact(&self, contact_uri: &str, username: &str, source_ip: Option<IpAddr>) -> Result<String, Status> {
+        let config = self.config.load();
+        contact::validate(contact_uri, &config.contact_rules()).map_err(|rejection| {
+            metrics::inc_counter("registrar_contact_rejected_total", &[("reason", rejection.as_str())]);
+            warn!(
+                event = "SIP_REGISTER_CONTACT_REJECT",
+                sip.user = %username,
+                source_ip = ?source_ip,
+                reason = rejection.as_str(),
+                contact_len = contact_uri.len(),
+                "Contact URI reddedildi"
+            );
+            ServiceError::InvalidContact(rejection).into()
+        })
+    }
+
+    /// Bilinen tarayıcı araçlarını (friendly-scanner, sipvicious...) reddeder; `tarpit`
+    /// modunda yanıt, RPC bütçesini aşmayacak kadar geciktirilir.
+    async fn screen_user_agent(&self, user_agent: Option<&str>, source_ip: Option<IpAddr>, deadline: &Deadline) -> Result<(), Status> {
+        let Some(ua) = user_agent else { return Ok(()) };
+        let config = self.config.load();
+        let Some(pattern) = policy::match_user_agent(&config.scanner_user_agent_patterns, ua) else { return Ok(()) };
+
+        // Tarpit yuvaları doluysa bekletmeden reddedilir; tarayıcı seli görev/bellek tüketemez.
+        let tarpit = match config.scanner_action.as_str() {
+            "tarpit" => self.guards.tarpit_slots.clone().try_acquire_owned().ok(),
+            _ => None,
+        };
+        let action = match (config.scanner_action.as_str(), &tarpit) {
+            ("tarpit", None) => "reject_saturated",
+            (action, _) => action,
+        };
+
+        metrics::inc_counter("registrar_scanner_blocked_total", &[("action", action)]);
+        warn!(
+            event = "SECURITY_SCANNER_BLOCKED",
+            audit = true,
+            user_agent = %ua,
+            pattern = %pattern,
+            source_ip = ?source_ip,
+            action = action,
+            "Tarayıcı User-Agent'ı engellendi"
+        );
+        if let Some(_slot) = tarpit {
+            tokio::time::sleep(Duration::from_millis(config.scanner_tarpit_ms).min(deadline.remaining())).await;
+        }
+        Err(ServiceError::ScannerBlocked(ua.to_string()).into())
+    }
+
+    /// Kilidi etkin (eşiği 0 olmayan) kaynaklar.
+    fn lockout_subjects<'a>(&self, aor: &'a str, source_ip: Option<&'a str>) -> Vec<(LockScope, &'a str, LockoutRule)> {
+        let config = self.config.load();
+        let rule = |threshold| LockoutRule {
+            threshold,
+            window: Duration::from_secs(config.brute_force_window_secs),
+            lockout: Duration::from_secs(config.brute_force_lockout_secs),
+        };
+        std::iter::once((LockScope::Aor, aor, rule(config.brute_force_aor_threshold)))
+            .chain(source_ip.map(|ip| (LockScope::SourceIp, ip, rule(config.brute_force_ip_threshold))))
+            .filter(|(_, _, rule)| rule.threshold > 0)
+            .collect()
+    }
+
+    /// Kilitli AOR veya kaynaktan gelen isteği kimlik doğrulamasına gitmeden reddeder.
+    /// Redis erişilemezse istek geçer (fail-open).
+    async fn check_lockout(&self, aor: &str, source_ip: Option<&str>, deadline: &Deadline, redis_timeout: Duration) -> Result<(), Status> {
+        let subjects: Vec<_> = self.lockout_subjects(aor, source_ip).into_iter().map(|(scope, subject, _)| (scope, subject)).collect();
+        let checked = deadline.run(redis_timeout, "redis", self.guards.lockouts.locked(&subjects)).await
+            .map_err(anyhow::Error::from)
+            .and_then(|r| r);
+        match checked {
+            Ok(None) => Ok(()),
+            Ok(Some((scope, remaining))) => {
+                metrics::inc_counter("registrar_lockout_rejected_total", &[("scope", scope.as_str())]);
+                warn!(
+                    event = "SECURITY_LOCKOUT_REJECT",
+                    audit = true,
+                    scope = scope.as_str(),
+                    aor = %aor,
+                    source_ip = ?source_ip,
+                    remaining_secs = remaining.as_secs(),
+                    "Kilitli kaynaktan REGISTER reddedildi"
+                );
+                Err(ServiceError::LockedOut { scope, retry_after: remaining }.into())
+            }
+            Err(e) => {
+                metrics::inc_counter("registrar_lockout_errors_total", &[]);
+                warn!(event="LOCKOUT_CHECK_FAIL", error=%e, "Kilit durumu okunamadı, istek geçiyor");
+                Ok(())
+            }
+        }
+    }
+
+    /// Başarısız kimlik doğrulamasını AOR ve kaynak IP sayaçlarına işler; eşik aşılınca kilitler.
+    async fn record_auth_failure(&self, aor: &str, source_ip: Option<&str>, trace_id: &str, deadline: &Deadline, redis_timeout: Duration) {
+        for (scope, subject, rule) in self.lockout_subjects(aor, source_ip) {
+            let recorded = deadline.run(redis_timeout, "redis", self.guards.lockouts.record_failure(scope, subject, rule)).await
+                .map_err(anyhow::Error::from)
+                .and_then(|r| r);
+            let outcome = match recorded {
+                Ok(outcome) => outcome,
+                Err(e) => {
+                    metrics::inc_counter("registrar_lockout_errors_total", &[]);
+                    warn!(event="LOCKOUT_RECORD_FAIL", scope=scope.as_str(), error=%e, "Başarısız deneme kaydedilemedi");
+                    continue;
+                }
+            };
+            if outcome.locked {
+                metrics::inc_counter("registrar_lockouts_total", &[("scope", scope.as_str())]);
+                warn!(
+                    event = "SECURITY_BRUTE_FORCE_LOCKOUT",
+                    audit = true,
+                    trace_id = %trace_id,
+                    scope = scope.as_str(),
+                    subject = %subject,
+                    failures = outcome.failures,
+                    window_secs = rule.window.as_secs(),
+                    lockout_secs = rule.lockout.as_secs(),
+                    "Art arda başarısız denemeler nedeniyle kilitlendi"
+                );
+            } else if outcome.failures * 2 >= rule.threshold as u64 {
+                // Eşiğin yarısı aşıldığında SIEM'in erken uyarı verebilmesi için.
+                warn!(
+                    event = "SECURITY_BRUTE_FORCE_SUSPECTED",
+                    audit = true,
+                    trace_id = %trace_id,
+                    scope = scope.as_str(),
+                    subject = %subject,
+                    failures = outcome.failures,
+                    threshold = rule.threshold,
+                    window_secs = rule.window.as_secs(),
+                    "Kayan pencerede başarısız deneme sayısı yükseliyor"
+                );
+            }
+        }
+    }
+
+    /// AOR, kaynak IP ve tenant kovalarından jeton düşer. Redis erişilemezse istek
+    /// sınırlanmadan geçer (fail-open); kayıt trafiği hız sınırı yüzünden durmaz.
+    async fn check_rate_limit(&self, aor: &str, source_ip: Option<IpAddr>, tenant: &str, deadline: &Deadline, redis_timeout: Duration) -> Result<(), Status> {
+        let config = self.config.load();
+        let mut buckets = vec![
+            (LimitScope::Aor, aor.to_string(), BucketLimit { per_min: config.rate_limit_aor_per_min, burst: config.rate_limit_aor_burst }),
+            (LimitScope::Tenant, tenant.to_string(), BucketLimit { per_min: config.rate_limit_tenant_per_min, burst: config.rate_limit_tenant_burst }),
+        ];
+        if let Some(ip) = source_ip {
+            buckets.push((LimitScope::SourceIp, ip.to_string(), BucketLimit { per_min: config.rate_limit_ip_per_min, burst: config.rate_limit_ip_burst }));
+        }
+
+        let checked = deadline.run(redis_timeout, "redis", self.guards.rate_limiter.check(&buckets)).await
+            .map_err(anyhow::Error::from)
+            .and_then(|r| r);
+        match checked {
+            Ok(None) => Ok(()),
+            Ok(Some(limited)) => {
+                metrics::inc_counter("registrar_register_rate_limited_total", &[("scope", limited.scope.as_str())]);
+                warn!(
+                    event = "SIP_REGISTER_RATE_LIMITED",
+                    aor = %aor,
+                    source_ip = ?source_ip,
+                    tenant.id = %tenant,
+                    scope = limited.scope.as_str(),
+                    retry_after_ms = limited.retry_after.as_millis() as u64,
+                    "REGISTER hız sınırına takıldı"
+                );
+                Err(ServiceError::RateLimited { scope: limited.scope, retry_after: limited.retry_after }.into())
+            }
+            Err(e) => {
+                metrics::inc_counter("registrar_rate_limit_errors_total", &[]);
+                warn!(event="RATE_LIMIT_CHECK_FAIL", error=%e, "Hız sınırı kontrol edilemedi, istek sınırlanmadan geçiyor");
+                Ok(())
+            }
+        }
+    }
+
+    /// Kimlik bilgisini önce önbellekten, yoksa User Service'ten alır. Kalıcı retler
+    /// (NOT_FOUND, PERMISSION_DENIED...) kısa süreli negatif kayıt olarak saklanır.
+    async fn fetch_credentials(&self, realm: String, username: &str, deadline: &Deadline, attempt_timeout: Duration) -> Result<GetSipCredentialsResponse, Status> {
+        if let Some(cached) = self.credential_cache.get(&realm, username) {
+            return cached.map_err(|(code, message)| Status::new(code, message));
+        }
+
+        let res = self.clients.user.get_sip_credentials(GetSipCredentialsRequest {
+            sip_username: username.to_string(),
+            realm: realm.clone(),
+        }, deadline, attempt_timeout).await;
+
+        match &res {
+            Ok(inner) => self.credential_cache.insert(&realm, username, Ok(inner.clone())),
+            Err(status) if !ServiceError::from_user_service(status.clone()).is_dependency_failure() => {
+                self.credential_cache.insert(&realm, username, Err((status.code(), status.message().to_string())));
+            }
+            Err(_) => {}
+        }
+        res
     }
     
-    // Trace ID Çıkarıcı
+    /// Degraded mode: User Service erişilemezken, hâlâ geçerli bir bağlamanın aynı
+    /// Contact, Call-ID ve cihaz örneğinden gelen yenilemesini, son başarılı kimlik
+    /// doğrulamasından itibaren `degraded_grace_secs` boyunca kabul eder. Verilen süreyi döner.
+    async fn try_degraded_refresh(&self, aor: &str, contact: &str, expires: i32, info: &BindingInfo, deadline: &Deadline) -> Option<i32> {
+        let config = self.config.load();
+        let redis_timeout = Duration::from_millis(config.redis_timeout_ms);
+        let reject = |reason: &str| {
+            metrics::inc_counter("registrar_register_degraded_total", &[("result", "rejected")]);
+            debug!(event="SIP_REGISTER_DEGRADED_REJECT", reason=%reason, "Degraded yenileme koşulları sağlanmadı");
+            None
+        };
+
+        let binding = match deadline.run(redis_timeout, "redis", self.store.binding(aor, &info.device_id(contact))).await {
+            Ok(Ok(Some(b))) => b,
+            Ok(Ok(None)) => return reject("no_binding"),
+            Ok(Err(e)) => {
+                warn!(event="SIP_REGISTER_DEGRADED_LOOKUP_FAIL", error=%e, "Bağlama okunamadı");
+                return reject("store_error");
+            }
+            Err(e) => {
+                warn!(event="SIP_REGISTER_DEGRADED_LOOKUP_FAIL", error=%e, "Bağlama okunamadı");
+                return reject("store_timeout");
+            }
+        };
+        if binding.contact != contact {
+            return reject("contact_mismatch");
+        }
+        if info.call_id.is_none() || binding.call_id != info.call_id {
+            return reject("call_id_mismatch");
+        }
+        if binding.instance != info.instance {
+            return reject("instance_mismatch");
+        }
+
+        let remaining = binding.authenticated_at + config.degraded_grace_secs as i64 - chrono::Utc::now().timestamp();
+        if remaining <= 0 {
+            return reject("grace_expired");
+        }
+        let granted = (expires as i64).min(remaining) as i32;
+        match deadline.run(redis_timeout, "redis", self.store.refresh_degraded(aor, &binding, granted)).await {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => {
+                warn!(event="SIP_REGISTER_DEGRADED_STORE_FAIL", error=%e, "Degraded yenileme yazılamadı");
+                return reject("store_error");
+            }
+            Err(e) => {
+                warn!(event="SIP_REGISTER_DEGRADED_STORE_FAIL", error=%e, "Degraded yenileme yazılamadı");
+                return reject("store_timeout");
+            }
+        }
+        metrics::inc_counter("registrar_register_degraded_total", &[("result", "accepted")]);
+        Some(granted)
+    }
+
+    /// İstekteki URI'nin kayıt anahtarı (AOR); kullanıcı adı çıkarılamazsa INVALID_ARGUMENT.
+    fn aor_of(&self, sip_uri: &str) -> Result<String, Status> {
+        self.policies.current().aor_from_uri(sip_uri, &self.config.load().sip_realm).ok_or_else(|| {
+            warn!(event="SIP_URI_PARSE_FAIL", uri=%sip_uri, "Geçersiz URI");
+            Status::invalid_argument("Invalid SIP URI")
+        })
+    }
+
+    /// Çağrının toplam bütçesi ve Redis işlemleri için süre sınırı.
+    fn deadlines(&self, metadata: &MetadataMap) -> (Deadline, Duration) {
+        let config = self.config.load();
+        (
+            Deadline::from_metadata(metadata, Duration::from_millis(config.rpc_default_deadline_ms)),
+            Duration::from_millis(config.redis_timeout_ms),
+        )
+    }
+
+    // Trace ID Çıkarıcı: W3C traceparent bağlamını aktif span'e bağlar (yoksa x-trace-id).
     fn extract_trace_id<T>(req: &Request<T>) -> String {
-        req.metadata().get("x-trace-id")
-            .and_then(|v| v.to_str().ok())
-            .unwrap_or("unknown")
-            .to_string()
+        telemetry::attach_remote_context(req.metadata())
     }
 }
 
 #[tonic::async_trait]
 impl RegistrarService for MyRegistrarService {
     
-    #[instrument(skip(self, request), fields(trace_id, sip.uri = %request.get_ref().sip_uri))]
+    #[instrument(skip(self, request), fields(trace_id, tenant.id, sip.uri = %request.get_ref().sip_uri))]
     async fn register(
         &self,
         request: Request<RegisterRequest>,
     ) -> Result<Response<RegisterResponse>, Status> {
         let trace_id = Self::extract_trace_id(&request);
         Span::current().record("trace_id", &trace_id);
+        let (deadline, redis_timeout) = self.deadlines(request.metadata());
+        
+        let digest_algorithm = metadata_str(&request, DIGEST_ALGORITHM_HEADER);
+        let call_id = metadata_str(&request, CALL_ID_HEADER);
+        let metadata_instance = metadata_str(&request, INSTANCE_HEADER);
+        let source_ip = metadata_str(&request, SOURCE_IP_HEADER).and_then(|ip| ip.parse::<IpAddr>().ok());
+        let user_agent = metadata_str(&request, USER_AGENT_HEADER);
         
         let req = request.into_inner();
+        let binding_info = BindingInfo {
+            call_id,
+            instance: policy::instance_from_contact(&req.contact_uri).or(metadata_instance),
+            user_agent,
+        };
+        self.screen_user_agent(binding_info.user_agent.as_deref(), source_ip, &deadline).await?;
         let username = sentiric_sip_core::utils::extract_username_from_uri(&req.sip_uri);
 
         if username.is_empty() {
             warn!(event="SIP_REGISTER_BAD_REQUEST", uri=%req.sip_uri, "Geçersiz URI");
             return Err(Status::invalid_argument("Invalid SIP URI"));
         }
+        // Kayıt silmede (expires=0) Contact yalnızca silinecek cihazı belirtir. `*` (RFC 3261
+        // 10.2.2) AOR'un tüm bağlamalarını siler; expires>0 ile doğrulamada reddedilir.
+        let wildcard = req.expires == 0 && req.contact_uri.trim() == "*";
+        let contact_uri = if req.expires > 0 {
+            self.check_contact(&req.contact_uri, &username, source_ip)?
+        } else {
+            req.contact_uri.trim().to_string()
+        };
 
-        // 1. User Service Sorgusu
-        let mut user_client = {
-            let guard = self.clients.lock().await;
-            guard.user.clone()
+        // 0. Realm Politikası
+        let config = self.config.load();
+        let realm = policy::realm_from_uri(&req.sip_uri).unwrap_or_else(|| config.sip_realm.clone());
+        let policies = self.policies.current();
+        let checked = policies.resolve(&realm).and_then(|p| {
+            let expires = p.effective_expires(req.expires)?;
+            if expires > 0 {
+                p.check_transport(&policy::transport_from_contact(&contact_uri))?;
+                p.check_user_agent(binding_info.user_agent.as_deref())?;
+            }
+            if let Some(alg) = &digest_algorithm {
+                p.check_digest_algorithm(alg)?;
+            }
+            Ok((p, expires))
+        });
+        let (realm_policy, expires) = match checked {
+            Ok(v) => v,
+            Err(violation) => {
+                warn!(event="SIP_REGISTER_POLICY_REJECT", sip.user=%username, realm=%realm, reason=%violation, "Realm politikası ihlali");
+                return Err(violation.into());
+            }
         };
+        // Politikası tanımlı realm'ler kendi adlarıyla, diğerleri varsayılan realm ile doğrulanır.
+        // AOR da bu realm'den kurulur; host'u değiştirmek kilit sayacını sıfırlamaz.
+        let credential_realm = policies.canonical_realm(&realm, &config.sip_realm);
 
-        // User Service'e de Trace ID'yi gönder (Metadata propagation Tonic ile otomatik olabilir ama explicit yapmak iyidir)
-        // Burada basitlik için doğrudan çağırıyoruz.
+        // 0.1 Kaynak ağ, hız sınırı ve kilit (kimlik doğrulamasından önce; tarayıcılar User Service'e ulaşmaz)
+        // Kimlik doğrulamasından önce tenant yalnızca politikadan bilinir; ağ listeleri ve tenant kovası bunu kullanır.
+        // URI host'u değil kanonik realm kullanılır; uydurma host'lar yeni tenant kovası açamaz.
+        let tenant = realm_policy.tenant_for(&credential_realm);
+        Span::current().record("tenant.id", tenant.as_str());
+        let aor = policy::aor(&username, &credential_realm);
+        self.check_source_network(&tenant, &aor, source_ip)?;
+        self.check_rate_limit(&aor, source_ip, &tenant, &deadline, redis_timeout).await?;
+        let source_ip_str = source_ip.map(|ip| ip.to_string());
+        self.check_lockout(&aor, source_ip_str.as_deref(), &deadline, redis_timeout).await?;
 
-        let user_res = user_client.get_sip_credentials(Request::new(GetSipCredentialsRequest {
-            sip_username: username.clone(),
-            realm: self.config.sip_realm.clone(),
-        })).await;
+        // 1. User Service Sorgusu (önbellek önünde)
+        let user_res = self.fetch_credentials(credential_realm, &username, &deadline, Duration::from_millis(config.user_service_timeout_ms)).await;
 
         match user_res {
-            Ok(res) => {
-                let inner = res.into_inner();
+            Ok(inner) => {
+                if let Some(configured) = realm_policy.tenant_id.as_deref().filter(|t| !inner.tenant_id.is_empty() && inner.tenant_id != *t) {
+                    warn!(
+                        event = "TENANT_MISMATCH",
+                        sip.user = %username,
+                        realm = %realm,
+                        tenant.id = %configured,
+                        user_service_tenant = %inner.tenant_id,
+                        "User Service'in tenant'ı politikadaki tenant_id ile uyuşmuyor; politika tenant'ı kullanılıyor"
+                    );
+                }
+                // Kotalar, metrikler ve loglar doğrulanmış tenant'ı kullanır.
+                let tenant = realm_policy.authenticated_tenant(&inner.tenant_id, tenant);
+                Span::current().record("tenant.id", tenant.as_str());
+                if config.brute_force_aor_threshold > 0 {
+                    if let Ok(Err(e)) = deadline.run(redis_timeout, "redis", self.guards.lockouts.reset_failures(LockScope::Aor, &aor)).await {
+                        debug!(event="LOCKOUT_RESET_FAIL", error=%e, "Başarısız deneme sayacı sıfırlanamadı");
+                    }
+                }
                 // [SUTS v4.0]: REGISTER SUCCESS
                 info!(
                     event = "SIP_REGISTER_SUCCESS",
                     trace_id = %trace_id,
                     sip.user = %username,
-                    tenant.id = %inner.tenant_id,
+                    tenant.id = %tenant,
                     "Kullanıcı doğrulandı ve kaydediliyor"
                 );
                 
-                // 2. Redis Kaydı
-                if let Err(e) = self.store.register_user(&req.sip_uri, &req.contact_uri, req.expires).await {
-                    error!(event="SIP_REGISTER_STORE_FAIL", user=%username, error=%e, "Redis yazma hatası");
-                    return Err(Status::internal("Location store failure"));
+                if wildcard {
+                    return match deadline.run(redis_timeout, "redis", self.store.unregister_user(&aor)).await {
+                        Ok(Ok(removed)) => {
+                            info!(event="SIP_UNREGISTER_ALL", trace_id=%trace_id, sip.user=%username, tenant.id=%tenant, devices=removed, "Contact: * ile AOR'un tüm bağlamaları silindi");
+                            Ok(Response::new(RegisterResponse { success: true }))
+                        }
+                        Ok(Err(e)) => {
+                            error!(event="SIP_REGISTER_STORE_FAIL", user=%username, error=%e, "Redis yazma hatası");
+                            Err(Status::internal("Location store failure"))
+                        }
+                        Err(e) => {
+                            error!(event="SIP_REGISTER_STORE_TIMEOUT", user=%username, error=%e, "Redis yazma zaman aşımı");
+                            Err(e.into())
+                        }
+                    };
+                }
+
+                // 2. Redis Kaydı (AOR ve tenant kotaları atomik olarak uygulanır)
+                let limits = realm_policy.binding_limits();
+                // Gauge yalnızca tanımlı tenant'lar (ve varsayılan realm) için tutulur; tenant
+                // User Service'ten gelebilir, etiket sayısı sınırsız büyümemeli. Tek bir `other`
+                // serisi son yazan tenant'ın sayısını gösterirdi; diğerleri `/admin/usage` ile izlenir.
+                let tenant_gauge = (policies.knows_tenant(&tenant) || tenant.eq_ignore_ascii_case(&config.sip_realm)).then_some(tenant.as_str());
+                match deadline.run(redis_timeout, "redis", self.store.register_user(&aor, &contact_uri, expires, &binding_info, &tenant, limits)).await {
+                    Ok(Ok(RegisterOutcome::Stored { evicted, tenant_bindings, .. })) => {
+                        if let Some(label) = tenant_gauge {
+                            metrics::set_gauge("registrar_tenant_registrations", &[("tenant", label)], tenant_bindings as f64);
+                        }
+                        for device in &evicted {
+                            metrics::inc_counter("registrar_binding_quota_total", &[("scope", "aor"), ("action", "evicted")]);
+                            info!(
+                                event = "SIP_BINDING_EVICTED",
+                                trace_id = %trace_id,
+                                sip.user = %username,
+                                tenant.id = %tenant,
+                                device = %device,
+                                max_devices = limits.max_per_aor,
+                                "Cihaz sınırı nedeniyle en eski bağlama silindi"
+                            );
+                        }
+                    }
+                    Ok(Ok(RegisterOutcome::QuotaExceeded { scope, aor_bindings, tenant_bindings })) => {
+                        if let Some(label) = tenant_gauge {
+                            metrics::set_gauge("registrar_tenant_registrations", &[("tenant", label)], tenant_bindings as f64);
+                        }
+                        metrics::inc_counter("registrar_binding_quota_total", &[("scope", scope.as_str()), ("action", "rejected")]);
+                        warn!(
+                            event = "SIP_REGISTER_QUOTA_EXCEEDED",
+                            trace_id = %trace_id,
+                            sip.user = %username,
+                            tenant.id = %tenant,
+                            scope = scope.as_str(),
+                            aor_bindings = aor_bindings,
+                            tenant_bindings = tenant_bindings,
+                            max_devices = limits.max_per_aor,
+                            max_tenant = limits.max_per_tenant,
+                            "Kayıt kotası dolu, yeni cihaz reddedildi"
+                        );
+                        return Err(ServiceError::QuotaExceeded { scope }.into());
+                    }
+                    Ok(Err(e)) => {
+                        error!(event="SIP_REGISTER_STORE_FAIL", user=%username, error=%e, "Redis yazma hatası");
+                        return Err(Status::internal("Location store failure"));
+                    }
+                    Err(e) => {
+                        error!(event="SIP_REGISTER_STORE_TIMEOUT", user=%username, error=%e, "Redis yazma zaman aşımı");
+                        return Err(e.into());
+                    }
                 }
                 
                 Ok(Response::new(RegisterResponse { success: true }))
             },
             Err(e) => {
-                // [SUTS v4.0]: AUTH FAILURE
-                warn!(
-                    event = "SIP_AUTH_FAILURE",
-                    trace_id = %trace_id,
-                    sip.user = %username,
-                    error = %e,
-                    "Kimlik doğrulama başarısız"
-                );
-                Err(Status::unauthenticated("Invalid credentials"))
+                let err = ServiceError::from_user_service(e);
+                if err.is_dependency_failure() && config.degraded_mode_enabled && expires > 0 {
+                    if let Some(granted) = self.try_degraded_refresh(&aor, &contact_uri, expires, &binding_info, &deadline).await {
+                        warn!(
+                            event = "SIP_REGISTER_DEGRADED",
+                            trace_id = %trace_id,
+                            sip.user = %username,
+                            expires = granted,
+                            reason = err.reason(),
+                            "User Service erişilemez; mevcut bağlama kimlik doğrulamasız uzatıldı"
+                        );
+                        return Ok(Response::new(RegisterResponse { success: true }));
+                    }
+                }
+                if err.is_dependency_failure() {
+                    // User Service'e ulaşılamıyor; bu bir kimlik doğrulama hatası değildir.
+                    error!(event="USER_SERVICE_UNAVAILABLE", trace_id=%trace_id, sip.user=%username, reason=err.reason(), error=%err, "User Service erişilemez");
+                } else {
+                    // [SUTS v4.0]: AUTH FAILURE
+                    warn!(
+                        event = "SIP_AUTH_FAILURE",
+                        audit = true,
+                        trace_id = %trace_id,
+                        sip.user = %username,
+                        reason = err.reason(),
+                        error = %err,
+                        "Kimlik doğrulama başarısız"
+                    );
+                    self.record_auth_failure(&aor, source_ip_str.as_deref(), &trace_id, &deadline, redis_timeout).await;
+                }
+                Err(err.into())
             }
         }
     }
@@ -106,13 +588,22 @@ impl RegistrarService for MyRegistrarService {
     async fn unregister(&self, request: Request<UnregisterRequest>) -> Result<Response<UnregisterResponse>, Status> {
         let trace_id = Self::extract_trace_id(&request);
         Span::current().record("trace_id", &trace_id);
+        let (deadline, redis_timeout) = self.deadlines(request.metadata());
         
         let req = request.into_inner();
         info!(event="SIP_UNREGISTER_REQUEST", uri=%req.sip_uri, "Kayıt silme isteği");
+        let aor = self.aor_of(&req.sip_uri)?;
         
-        if let Err(e) = self.store.unregister_user(&req.sip_uri).await {
-            error!(event="SIP_UNREGISTER_FAIL", error=%e, "Silme hatası");
-            return Err(Status::internal("Location store failure"));
+        match deadline.run(redis_timeout, "redis", self.store.unregister_user(&aor)).await {
+            Ok(Ok(_)) => {}
+            Ok(Err(e)) => {
+                error!(event="SIP_UNREGISTER_FAIL", error=%e, "Silme hatası");
+                return Err(Status::internal("Location store failure"));
+            }
+            Err(e) => {
+                error!(event="SIP_UNREGISTER_TIMEOUT", error=%e, "Silme zaman aşımı");
+                return Err(e.into());
+            }
         }
         
         Ok(Response::new(UnregisterResponse { success: true }))
@@ -122,13 +613,36 @@ impl RegistrarService for MyRegistrarService {
     async fn lookup_contact(&self, request: Request<LookupContactRequest>) -> Result<Response<LookupContactResponse>, Status> {
         let trace_id = Self::extract_trace_id(&request);
         Span::current().record("trace_id", &trace_id);
+        let (deadline, redis_timeout) = self.deadlines(request.metadata());
         
         let req = request.into_inner();
-        let contact = self.store.lookup_user(&req.sip_uri).await;
+        let aor = self.aor_of(&req.sip_uri)?;
+        let bindings = match deadline.run(redis_timeout, "redis", self.store.lookup_user(&aor)).await {
+            Ok(Ok(bindings)) => bindings,
+            Ok(Err(e)) => {
+                warn!(event="SIP_LOOKUP_FAIL", uri=%req.sip_uri, error=%e, "Konum okunamadı");
+                Vec::new()
+            }
+            Err(e) => {
+                error!(event="SIP_LOOKUP_TIMEOUT", uri=%req.sip_uri, error=%e, "Konum sorgusu zaman aşımı");
+                return Err(e.into());
+            }
+        };
 
-        if let Some(c) = contact {
-            info!(event="SIP_LOOKUP_HIT", uri=%req.sip_uri, contact=%c, "Kullanıcı bulundu");
-            Ok(Response::new(LookupContactResponse { contact_uris: vec![c] }))
+        if !bindings.is_empty() {
+            info!(event="SIP_LOOKUP_HIT", uri=%req.sip_uri, contact=%bindings[0].contact, bindings=bindings.len(), "Kullanıcı bulundu");
+            let contact_uris = bindings.iter().map(|b| b.contact.clone()).collect();
+            let mut response = Response::new(LookupContactResponse { contact_uris });
+            // Cihaz bilgisi contact_uris sırasıyla, bağlama başına bir değer olarak eklenir.
+            let metadata = response.metadata_mut();
+            for binding in bindings {
+                for (key, value) in [(DEVICE_USER_AGENT_HEADER, binding.user_agent), (DEVICE_INSTANCE_HEADER, binding.instance)] {
+                    let value = value.and_then(|v| MetadataValue::try_from(v.as_str()).ok())
+                        .unwrap_or_else(|| MetadataValue::from_static("-"));
+                    metadata.append(key, value);
+                }
+            }
+            Ok(response)
         } else {
             info!(event="SIP_LOOKUP_MISS", uri=%req.sip_uri, "Kullanıcı bulunamadı (Offline)");
             Ok(Response::new(LookupContactResponse { contact_uris: vec![] }))
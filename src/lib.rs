@@ -1,7 +1,12 @@
+pub mod admin;
 pub mod app;
 pub mod config;
+pub mod contact;
 pub mod error;
 pub mod grpc;
 pub mod tls;
 pub mod data; 
+pub mod metrics;
+pub mod policy;
+pub mod reload;
 pub mod telemetry; // YENİ
\ No newline at end of file
@@ -1,9 +1,25 @@
 // sentiric-registrar-service/src/main.rs
 use anyhow::{Context, Result};
 use sentiric_registrar_service::app::App;
+use sentiric_registrar_service::config::AppConfig;
 use std::process;
 
 fn main() -> Result<()> {
+    // --print-config: etkin yapılandırmayı (gizli alanlar maskeli) yazdırıp çıkar.
+    if std::env::args().any(|a| a == "--print-config") {
+        dotenvy::dotenv().ok();
+        match AppConfig::load() {
+            Ok(config) => {
+                println!("{}", config.to_redacted_json()?);
+                return Ok(());
+            }
+            Err(e) => {
+                eprintln!("Kritik Hata: Yapılandırma geçersiz: {:?}", e);
+                process::exit(1);
+            }
+        }
+    }
+
     // Rust'taki tonic/tokio yapısı gereği main asenkron olamaz.
     let runtime = tokio::runtime::Builder::new_multi_thread()
         .enable_all()
@@ -0,0 +1,143 @@
+// src/metrics.rs
+use arc_swap::ArcSwap;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+
+/// Tek bir seri: değer `f64` bitleri olarak atomik tutulur.
+struct Series {
+    name: &'static str,
+    kind: &'static str,
+    labels: Vec<(String, String)>,
+    value: AtomicU64,
+}
+
+impl Series {
+    fn matches(&self, name: &str, labels: &[(&str, &str)]) -> bool {
+        self.name == name
+            && self.labels.len() == labels.len()
+            && self.labels.iter().zip(labels).all(|((k, v), (lk, lv))| k == lk && v == lv)
+    }
+
+    fn update(&self, f: impl Fn(f64) -> f64) {
+        let _ = self.value.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |bits| Some(f(f64::from_bits(bits)).to_bits()));
+    }
+}
+
+/// Süreç içi metrik kaydı; `/metrics` ucunda Prometheus metin formatında sunulur.
+/// Seri bir kez kaydedildikten sonra güncelleme kilitsizdir (anahtar özeti + atomik değer);
+/// kilit yalnızca yeni bir etiket kombinasyonu ilk kez görüldüğünde alınır.
+struct Registry {
+    // (metrik adı, etiketler) özeti -> seriler (çakışmalar için liste)
+    series: ArcSwap<HashMap<u64, Vec<Arc<Series>>>>,
+    register_lock: Mutex<()>,
+}
+
+fn registry() -> &'static Registry {
+    static REGISTRY: OnceLock<Registry> = OnceLock::new();
+    REGISTRY.get_or_init(|| Registry { series: ArcSwap::from_pointee(HashMap::new()), register_lock: Mutex::new(()) })
+}
+
+fn series_hash(name: &str, labels: &[(&str, &str)]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    name.hash(&mut hasher);
+    labels.hash(&mut hasher);
+    hasher.finish()
+}
+
+fn update(name: &'static str, kind: &'static str, labels: &[(&str, &str)], f: impl Fn(f64) -> f64) {
+    let reg = registry();
+    let hash = series_hash(name, labels);
+    if let Some(series) = reg.series.load().get(&hash).and_then(|bucket| bucket.iter().find(|s| s.matches(name, labels))) {
+        series.update(f);
+        return;
+    }
+
+    let _guard = reg.register_lock.lock().unwrap_or_else(|e| e.into_inner());
+    let current = reg.series.load_full();
+    // Kilidi beklerken başka bir iş parçacığı aynı seriyi kaydetmiş olabilir.
+    if let Some(series) = current.get(&hash).and_then(|bucket| bucket.iter().find(|s| s.matches(name, labels))) {
+        series.update(f);
+        return;
+    }
+    let series = Arc::new(Series {
+        name,
+        kind,
+        labels: labels.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect(),
+        value: AtomicU64::new(0f64.to_bits()),
+    });
+    series.update(f);
+    let mut next = (*current).clone();
+    next.entry(hash).or_default().push(series);
+    reg.series.store(Arc::new(next));
+}
+
+pub fn inc_counter(name: &'static str, labels: &[(&str, &str)]) {
+    update(name, "counter", labels, |v| v + 1.0);
+}
+
+pub fn set_gauge(name: &'static str, labels: &[(&str, &str)], value: f64) {
+    update(name, "gauge", labels, |_| value);
+}
+
+pub fn add_gauge(name: &'static str, labels: &[(&str, &str)], delta: f64) {
+    update(name, "gauge", labels, |v| v + delta);
+}
+
+fn render_labels(labels: &[(String, String)]) -> String {
+    if labels.is_empty() {
+        return String::new();
+    }
+    let inner: Vec<String> = labels
+        .iter()
+        .map(|(k, v)| format!("{}=\"{}\"", k, v.replace('\\', "\\\\").replace('"', "\\\"")))
+        .collect();
+    format!("{{{}}}", inner.join(","))
+}
+
+/// Tüm serileri Prometheus metin formatında döner.
+pub fn render() -> String {
+    let snapshot = registry().series.load_full();
+    let sorted: BTreeMap<(&str, String), (&str, f64)> = snapshot
+        .values()
+        .flatten()
+        .map(|s| ((s.name, render_labels(&s.labels)), (s.kind, f64::from_bits(s.value.load(Ordering::Relaxed)))))
+        .collect();
+
+    let mut out = String::new();
+    let mut last_name = "";
+    for ((name, labels), (kind, value)) in &sorted {
+        if *name != last_name {
+            let _ = writeln!(out, "# TYPE {} {}", name, kind);
+            last_name = name;
+        }
+        let _ = writeln!(out, "{}{} {}", name, labels, value);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn concurrent_updates_are_not_lost() {
+        let threads: Vec<_> = (0..8)
+            .map(|_| std::thread::spawn(|| (0..1000).for_each(|_| inc_counter("test_metrics_concurrent_total", &[("result", "ok")]))))
+            .collect();
+        threads.into_iter().for_each(|t| t.join().unwrap());
+        assert!(render().contains("test_metrics_concurrent_total{result=\"ok\"} 8000\n"));
+    }
+
+    #[test]
+    fn gauges_and_labels_render_in_prometheus_format() {
+        set_gauge("test_metrics_gauge", &[("target", "a\"b")], 3.0);
+        add_gauge("test_metrics_gauge", &[("target", "a\"b")], -1.5);
+        let out = render();
+        assert!(out.contains("# TYPE test_metrics_gauge gauge\n"));
+        assert!(out.contains("test_metrics_gauge{target=\"a\\\"b\"} 1.5\n"));
+    }
+}
@@ -0,0 +1,530 @@
+// src/policy.rs
+use crate::data::store::{BindingLimits, RedisConn};
+use anyhow::{Context, Result};
+use arc_swap::ArcSwap;
+use redis::AsyncCommands;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tonic::Status;
+use tracing::{info, warn};
+
+/// Bir realm (tenant) için REGISTER kuralları.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RealmPolicy {
+    /// Bu realm'e ait tenant. Boşsa kimlik doğrulamasından önce (ağ listeleri, tenant hız
+    /// kovası) realm, sonra (kotalar, metrikler, loglar) user-service'in döndürdüğü tenant kullanılır.
+    pub tenant_id: Option<String>,
+    pub min_expires: i32,
+    pub max_expires: i32,
+    /// AOR başına en fazla eşzamanlı cihaz (0 = sınırsız).
+    pub max_devices_per_user: u32,
+    /// AOR sınırı doluyken yeni cihaz geldiğinde yapılacak işlem.
+    pub device_overflow: OverflowPolicy,
+    /// Tenant genelinde en fazla etkin kayıt (0 = sınırsız). Dolu tenant'ta yeni cihaz
+    /// her zaman reddedilir; başka kullanıcıların cihazları atılmaz.
+    pub max_registrations_per_tenant: u32,
+    /// İzin verilen digest algoritmaları (ör. MD5, SHA-256); boşsa hepsi.
+    pub allowed_digest_algorithms: Vec<String>,
+    /// İzin verilen Contact transport'ları (udp, tcp, tls, ws, wss); boşsa hepsi.
+    pub allowed_transports: Vec<String>,
+    /// Onaylı cihaz modelleri (User-Agent glob kalıpları, ör. `Yealink SIP-T4*`); boşsa hepsi.
+    pub allowed_user_agents: Vec<String>,
+}
+
+impl Default for RealmPolicy {
+    fn default() -> Self {
+        Self {
+            tenant_id: None,
+            min_expires: 60,
+            max_expires: 3600,
+            max_devices_per_user: 0,
+            device_overflow: OverflowPolicy::Reject,
+            max_registrations_per_tenant: 0,
+            allowed_digest_algorithms: Vec::new(),
+            allowed_transports: Vec::new(),
+            allowed_user_agents: Vec::new(),
+        }
+    }
+}
+
+/// Cihaz sınırı aşıldığında: yeni kaydı reddet ya da süresi en erken dolacak bağlamayı at.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OverflowPolicy {
+    #[default]
+    Reject,
+    EvictOldest,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PolicyViolation {
+    RealmNotAllowed(String),
+    ExpiresTooBrief { min: i32 },
+    DigestAlgorithmNotAllowed(String),
+    TransportNotAllowed(String),
+    DeviceNotAllowed(String),
+    SourceNotAllowed(String),
+}
+
+impl std::fmt::Display for PolicyViolation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::RealmNotAllowed(realm) => write!(f, "Realm not allowed: {}", realm),
+            Self::ExpiresTooBrief { min } => write!(f, "Interval too brief, min-expires={}", min),
+            Self::DigestAlgorithmNotAllowed(alg) => write!(f, "Digest algorithm not allowed: {}", alg),
+            Self::TransportNotAllowed(t) => write!(f, "Transport not allowed: {}", t),
+            Self::DeviceNotAllowed(ua) => write!(f, "Device not allowed: {}", ua),
+            Self::SourceNotAllowed(ip) => write!(f, "Source address not allowed: {}", ip),
+        }
+    }
+}
+
+impl From<PolicyViolation> for Status {
+    fn from(v: PolicyViolation) -> Self {
+        match v {
+            PolicyViolation::ExpiresTooBrief { .. } => Status::out_of_range(v.to_string()),
+            _ => Status::permission_denied(v.to_string()),
+        }
+    }
+}
+
+impl RealmPolicy {
+    /// Kimlik doğrulamasından önce bilinen tenant: politikadaki `tenant_id`, yoksa realm.
+    /// Ağ listeleri ve tenant hız kovası bu değerle anahtarlanır.
+    pub fn tenant_for(&self, realm: &str) -> String {
+        self.tenant_id.clone().unwrap_or_else(|| realm.to_string())
+    }
+
+    /// Kimlik doğrulamasından sonraki tenant: politikadaki `tenant_id`, yoksa user-service'in
+    /// döndürdüğü, o da boşsa kimlik doğrulamasından önceki tenant.
+    pub fn authenticated_tenant(&self, user_service_tenant: &str, pre_auth: String) -> String {
+        match &self.tenant_id {
+            Some(tenant) => tenant.clone(),
+            None if !user_service_tenant.is_empty() => user_service_tenant.to_string(),
+            None => pre_auth,
+        }
+    }
+
+    pub fn binding_limits(&self) -> BindingLimits {
+        BindingLimits {
+            max_per_aor: self.max_devices_per_user,
+            max_per_tenant: self.max_registrations_per_tenant,
+            evict_oldest: self.device_overflow == OverflowPolicy::EvictOldest,
+        }
+    }
+
+    /// İstenen expires değerini doğrular; üst sınırı aşan değerler kırpılır.
+    /// 0 (kayıt silme) her zaman kabul edilir.
+    pub fn effective_expires(&self, requested: i32) -> Result<i32, PolicyViolation> {
+        if requested <= 0 {
+            return Ok(requested);
+        }
+        if requested < self.min_expires {
+            return Err(PolicyViolation::ExpiresTooBrief { min: self.min_expires });
+        }
+        Ok(requested.min(self.max_expires))
+    }
+
+    pub fn check_digest_algorithm(&self, algorithm: &str) -> Result<(), PolicyViolation> {
+        if self.allowed_digest_algorithms.is_empty()
+            || self.allowed_digest_algorithms.iter().any(|a| a.eq_ignore_ascii_case(algorithm))
+        {
+            Ok(())
+        } else {
+            Err(PolicyViolation::DigestAlgorithmNotAllowed(algorithm.to_string()))
+        }
+    }
+
+    pub fn check_transport(&self, transport: &str) -> Result<(), PolicyViolation> {
+        if self.allowed_transports.is_empty()
+            || self.allowed_transports.iter().any(|t| t.eq_ignore_ascii_case(transport))
+        {
+            Ok(())
+        } else {
+            Err(PolicyViolation::TransportNotAllowed(transport.to_string()))
+        }
+    }
+
+    /// Allow-list tanımlıysa User-Agent'ı olmayan veya listeye uymayan cihazlar reddedilir.
+    pub fn check_user_agent(&self, user_agent: Option<&str>) -> Result<(), PolicyViolation> {
+        if self.allowed_user_agents.is_empty() {
+            return Ok(());
+        }
+        match user_agent {
+            Some(ua) if match_user_agent(&self.allowed_user_agents, ua).is_some() => Ok(()),
+            other => Err(PolicyViolation::DeviceNotAllowed(other.unwrap_or("-").to_string())),
+        }
+    }
+}
+
+/// Realm -> politika eşlemesi. `realms` içinde olmayan bir realm, `allow_unknown_realms`
+/// açıksa `default` politikayla işlenir, değilse reddedilir.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PolicySet {
+    pub allow_unknown_realms: bool,
+    pub default: RealmPolicy,
+    pub realms: HashMap<String, RealmPolicy>,
+}
+
+impl Default for PolicySet {
+    fn default() -> Self {
+        Self {
+            allow_unknown_realms: true,
+            default: RealmPolicy::default(),
+            realms: HashMap::new(),
+        }
+    }
+}
+
+impl PolicySet {
+    pub fn resolve(&self, realm: &str) -> Result<&RealmPolicy, PolicyViolation> {
+        if let Some(policy) = self.realms.get(&realm.to_ascii_lowercase()) {
+            return Ok(policy);
+        }
+        if self.allow_unknown_realms {
+            Ok(&self.default)
+        } else {
+            Err(PolicyViolation::RealmNotAllowed(realm.to_string()))
+        }
+    }
+
+    pub fn is_configured(&self, realm: &str) -> bool {
+        self.realms.contains_key(&realm.to_ascii_lowercase())
+    }
+
+    /// Kimlik doğrulamasının ve AOR'un realm'i: politikası tanımlı realm'ler kendi adıyla,
+    /// diğerleri varsayılan realm ile. URI'deki host kimlik doğrulanmadan gelir; tanımsız
+    /// host'lar aynı hesap için yeni AOR (kilit sayacı, hız kovası, cihaz kotası) açamaz.
+    pub fn canonical_realm(&self, realm: &str, default_realm: &str) -> String {
+        if self.is_configured(realm) { realm.to_ascii_lowercase() } else { default_realm.to_string() }
+    }
+
+    /// Tenant, tanımlı bir realm'in ya da bir politikanın `tenant_id`'si mi? Politikasız
+    /// realm'ler varsayılan realm'e düştüğü için bunun dışındaki tek tenant varsayılan realm'dir.
+    pub fn knows_tenant(&self, tenant: &str) -> bool {
+        self.is_configured(tenant)
+            || std::iter::once(&self.default)
+                .chain(self.realms.values())
+                .any(|p| p.tenant_id.as_deref().is_some_and(|t| t.eq_ignore_ascii_case(tenant)))
+    }
+
+    /// URI'nin AOR'u. Realm REGISTER'daki gibi kanonikleştirilir: realm'siz URI'ler ve
+    /// politikası tanımlı olmayan host'lar varsayılan realm'e düşer. Kullanıcı adı yoksa `None`.
+    pub fn aor_from_uri(&self, uri: &str, default_realm: &str) -> Option<String> {
+        let username = sentiric_sip_core::utils::extract_username_from_uri(uri);
+        if username.is_empty() {
+            return None;
+        }
+        let realm = realm_from_uri(uri).map_or_else(|| default_realm.to_string(), |realm| self.canonical_realm(&realm, default_realm));
+        Some(aor(&username, &realm))
+    }
+
+    /// Realm başına tutarlılık hatalarını döner (ör. min_expires > max_expires).
+    pub fn validate(&self) -> Vec<String> {
+        let mut errors = Vec::new();
+        for (realm, p) in std::iter::once(("default", &self.default)).chain(self.realms.iter().map(|(k, v)| (k.as_str(), v))) {
+            if p.min_expires < 0 || p.min_expires > p.max_expires {
+                errors.push(format!(
+                    "policies.{}: min_expires ({}) 0 ile max_expires ({}) arasında olmalı",
+                    realm, p.min_expires, p.max_expires
+                ));
+            }
+            if p.max_registrations_per_tenant > 0 && p.max_devices_per_user > p.max_registrations_per_tenant {
+                errors.push(format!(
+                    "policies.{}: max_devices_per_user ({}) max_registrations_per_tenant ({}) değerini aşamaz",
+                    realm, p.max_devices_per_user, p.max_registrations_per_tenant
+                ));
+            }
+        }
+        errors
+    }
+
+    /// Realm anahtarlarını küçük harfe normalize eder.
+    fn normalized(mut self) -> Self {
+        self.realms = self.realms.into_iter().map(|(k, v)| (k.to_ascii_lowercase(), v)).collect();
+        self
+    }
+}
+
+/// Paylaşılan, yeniden yüklenebilir politika kümesi. Etkin küme, yapılandırmadan gelen
+/// temel kümenin üzerine Redis'ten gelen realm politikalarının yazılmasıyla oluşur.
+#[derive(Clone)]
+pub struct PolicyStore {
+    base: Arc<ArcSwap<PolicySet>>,
+    overlay: Arc<ArcSwap<HashMap<String, RealmPolicy>>>,
+    current: Arc<ArcSwap<PolicySet>>,
+    rebuild_lock: Arc<Mutex<()>>,
+}
+
+impl PolicyStore {
+    pub fn new(base: PolicySet) -> Self {
+        let base = Arc::new(base.normalized());
+        Self {
+            current: Arc::new(ArcSwap::new(base.clone())),
+            base: Arc::new(ArcSwap::new(base)),
+            overlay: Arc::new(ArcSwap::from_pointee(HashMap::new())),
+            rebuild_lock: Arc::new(Mutex::new(())),
+        }
+    }
+
+    pub fn current(&self) -> Arc<PolicySet> {
+        self.current.load_full()
+    }
+
+    /// Yapılandırmadan gelen temel kümeyi değiştirir (config reload).
+    pub fn set_base(&self, base: PolicySet) {
+        self.base.store(Arc::new(base.normalized()));
+        self.rebuild();
+    }
+
+    /// Redis hash'indeki (`alan = realm`, `değer = RealmPolicy JSON`) politikaları
+    /// yapılandırmadaki politikaların üzerine yazarak yeni kümeyi etkinleştirir.
+    /// Ayrıştırılamayan kayıtlar loglanır ve atlanır.
+    pub async fn reload_from_redis(&self, mut conn: RedisConn, hash_key: &str) -> Result<usize> {
+        let raw: HashMap<String, String> = conn.hgetall(hash_key).await
+            .context("Politika hash'i okunamadı")?;
+
+        let mut overlay = HashMap::new();
+        for (realm, json) in raw {
+            match serde_json::from_str::<RealmPolicy>(&json) {
+                Ok(policy) => {
+                    overlay.insert(realm.to_ascii_lowercase(), policy);
+                }
+                Err(e) => warn!(event="POLICY_PARSE_FAIL", realm=%realm, error=%e, "Realm politikası ayrıştırılamadı, atlanıyor"),
+            }
+        }
+
+        let loaded = overlay.len();
+        self.overlay.store(Arc::new(overlay));
+        self.rebuild();
+        info!(event="POLICY_RELOADED", source="redis", key=%hash_key, realms=loaded, "Realm politikaları yüklendi");
+        Ok(loaded)
+    }
+
+    fn rebuild(&self) {
+        let _guard = self.rebuild_lock.lock().unwrap_or_else(|e| e.into_inner());
+        let mut next = (*self.base.load_full()).clone();
+        next.realms.extend(self.overlay.load().iter().map(|(k, v)| (k.clone(), v.clone())));
+        self.current.store(Arc::new(next));
+    }
+}
+
+/// Kayıtların, hız sınırlarının ve kilitlerin anahtarı olan AOR: `kullanıcı@realm`, küçük harf.
+pub fn aor(username: &str, realm: &str) -> String {
+    format!("{}@{}", username, realm).to_ascii_lowercase()
+}
+
+
+/// `sip:user@realm;params` biçimindeki URI'den realm (host) kısmını çıkarır.
+pub fn realm_from_uri(uri: &str) -> Option<String> {
+    let (_, rest) = uri.split_once('@')?;
+    let hostport = rest.split([';', '>', '?']).next()?.trim();
+    let host = if hostport.starts_with('[') {
+        hostport.split_inclusive(']').next().unwrap_or(hostport)
+    } else {
+        match hostport.rsplit_once(':') {
+            Some((host, port)) if port.chars().all(|c| c.is_ascii_digit()) => host,
+            _ => hostport,
+        }
+    };
+    if host.is_empty() { None } else { Some(host.to_ascii_lowercase()) }
+}
+
+/// User-Agent'a uyan ilk kalıbı döner. Kalıplar büyük/küçük harf duyarsız, `*` joker
+/// karakterli ve tüm değere uygulanır (ör. `*friendly-scanner*`).
+pub fn match_user_agent<'a>(patterns: &'a [String], user_agent: &str) -> Option<&'a str> {
+    patterns.iter().map(String::as_str).find(|p| glob_match(p, user_agent))
+}
+
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let p: Vec<char> = pattern.to_lowercase().chars().collect();
+    let t: Vec<char> = text.to_lowercase().chars().collect();
+    let (mut pi, mut ti) = (0, 0);
+    // Son görülen `*` ve o sırada eşlenen metin konumu (geri izleme için).
+    let mut star: Option<(usize, usize)> = None;
+    while ti < t.len() {
+        if pi < p.len() && p[pi] == '*' {
+            star = Some((pi, ti));
+            pi += 1;
+        } else if pi < p.len() && p[pi] == t[ti] {
+            pi += 1;
+            ti += 1;
+        } else if let Some((sp, st)) = star {
+            pi = sp + 1;
+            ti = st + 1;
+            star = Some((sp, st + 1));
+        } else {
+            return false;
+        }
+    }
+    p[pi..].iter().all(|c| *c == '*')
+}
+
+/// Contact'taki `+sip.instance="<urn:uuid:...>"` parametresini (tırnak ve köşeli parantezsiz) döner.
+pub fn instance_from_contact(contact: &str) -> Option<String> {
+    contact
+        .split(';')
+        .skip(1)
+        .filter_map(|param| param.split_once('='))
+        .find(|(k, _)| k.trim().eq_ignore_ascii_case("+sip.instance"))
+        .map(|(_, v)| v.trim().trim_matches(|c| matches!(c, '"' | '<' | '>')).to_string())
+        .filter(|v| !v.is_empty())
+}
+
+/// Contact URI'nin `;transport=` parametresini döner (varsayılan: udp, sips için tls).
+pub fn transport_from_contact(contact: &str) -> String {
+    contact
+        .split(';')
+        .skip(1)
+        .filter_map(|param| param.split_once('='))
+        .find(|(k, _)| k.trim().eq_ignore_ascii_case("transport"))
+        .map(|(_, v)| v.trim_end_matches('>').trim().to_ascii_lowercase())
+        .unwrap_or_else(|| {
+            if contact.trim_start_matches('<').to_ascii_lowercase().starts_with("sips:") {
+                "tls".to_string()
+            } else {
+                "udp".to_string()
+            }
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn device_limit_reaches_the_store() {
+        let policy = RealmPolicy { max_devices_per_user: 3, device_overflow: OverflowPolicy::EvictOldest, max_registrations_per_tenant: 100, ..Default::default() };
+        let limits = policy.binding_limits();
+        assert_eq!(limits.max_per_aor, 3);
+        assert_eq!(limits.max_per_tenant, 100);
+        assert!(limits.evict_oldest);
+
+        let unlimited = RealmPolicy::default().binding_limits();
+        assert_eq!((unlimited.max_per_aor, unlimited.evict_oldest), (0, false));
+    }
+
+    #[test]
+    fn device_limit_above_tenant_quota_is_rejected() {
+        let mut set = PolicySet::default();
+        set.realms.insert("acme.example".into(), RealmPolicy { max_devices_per_user: 10, max_registrations_per_tenant: 5, ..Default::default() });
+        let errors = set.validate();
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].contains("max_devices_per_user"));
+    }
+
+    #[test]
+    fn tenant_is_resolved_from_policy_or_realm() {
+        let mut set = PolicySet::default();
+        set.realms.insert("acme.example".into(), RealmPolicy { tenant_id: Some("acme".into()), ..Default::default() });
+        set.realms.insert("beta.example".into(), RealmPolicy::default());
+
+        assert_eq!(set.resolve("acme.example").unwrap().tenant_for("acme.example"), "acme");
+        assert_eq!(set.resolve("beta.example").unwrap().tenant_for("beta.example"), "beta.example");
+        assert!(set.knows_tenant("ACME"));
+
+        // Politika tenant'ı esastır; yoksa User Service'in tenant'ı, o da yoksa realm.
+        let acme = set.resolve("acme.example").unwrap();
+        assert_eq!(acme.authenticated_tenant("other", "acme".into()), "acme");
+        let beta = set.resolve("beta.example").unwrap();
+        assert_eq!(beta.authenticated_tenant("globex", "beta.example".into()), "globex");
+        assert_eq!(beta.authenticated_tenant("", "beta.example".into()), "beta.example");
+        assert!(set.knows_tenant("beta.example"));
+        assert!(!set.knows_tenant("gamma"));
+    }
+
+    #[test]
+    fn expires_is_clamped_and_unregister_passes() {
+        let policy = RealmPolicy { min_expires: 60, max_expires: 600, ..Default::default() };
+        assert_eq!(policy.effective_expires(0), Ok(0));
+        assert_eq!(policy.effective_expires(30), Err(PolicyViolation::ExpiresTooBrief { min: 60 }));
+        assert_eq!(policy.effective_expires(3600), Ok(600));
+    }
+
+    #[test]
+    fn unknown_realm_uses_default_only_when_allowed() {
+        let mut set = PolicySet::default();
+        set.realms.insert("acme.example".into(), RealmPolicy::default());
+        let set = set.normalized();
+        assert!(set.resolve("ACME.example").is_ok());
+        assert!(set.resolve("other.example").is_ok());
+
+        let strict = PolicySet { allow_unknown_realms: false, ..set };
+        assert_eq!(strict.resolve("other.example").err(), Some(PolicyViolation::RealmNotAllowed("other.example".into())));
+    }
+
+    #[test]
+    fn unconfigured_hosts_share_the_default_realm() {
+        let mut set = PolicySet::default();
+        set.realms.insert("acme.example".into(), RealmPolicy::default());
+        assert_eq!(set.canonical_realm("acme.example", "sentiric_demo"), "acme.example");
+        // Saldırgan host'u her denemede değiştirse de aynı AOR'a (ve kilit sayacına) düşer.
+        let forged: Vec<String> = ["1.2.3.4", "x.invalid", "y.invalid"]
+            .iter()
+            .map(|host| aor("1001", &set.canonical_realm(host, "sentiric_demo")))
+            .collect();
+        assert!(forged.iter().all(|a| a == "1001@sentiric_demo"));
+        // Lookup ve unregister da aynı AOR'u bulur.
+        assert_eq!(set.aor_from_uri("sip:1001@10.0.0.1:5060", "sentiric_demo").as_deref(), Some("1001@sentiric_demo"));
+        assert_eq!(set.aor_from_uri("sip:1001@ACME.example", "sentiric_demo").as_deref(), Some("1001@acme.example"));
+    }
+
+    #[test]
+    fn aor_is_normalized() {
+        assert_eq!(aor("Alice", "Acme.Example"), "alice@acme.example");
+    }
+
+    #[test]
+    fn realm_is_extracted_from_uri() {
+        assert_eq!(realm_from_uri("sip:alice@Acme.Example"), Some("acme.example".into()));
+        assert_eq!(realm_from_uri("sip:alice@acme.example:5061;transport=tls"), Some("acme.example".into()));
+        assert_eq!(realm_from_uri("<sip:alice@acme.example>"), Some("acme.example".into()));
+        assert_eq!(realm_from_uri("sip:alice@[2001:db8::1]:5060"), Some("[2001:db8::1]".into()));
+        assert_eq!(realm_from_uri("sip:alice@"), None);
+        assert_eq!(realm_from_uri("sip:acme.example"), None);
+    }
+
+    #[test]
+    fn glob_edge_cases() {
+        assert!(glob_match("*", ""));
+        assert!(glob_match("*", "anything"));
+        assert!(glob_match("", ""));
+        assert!(!glob_match("", "x"));
+        assert!(glob_match("**", "x"));
+        assert!(glob_match("*friendly-scanner*", "Friendly-Scanner 1.0"));
+        assert!(glob_match("smap*", "smap 0.6.0"));
+        assert!(!glob_match("smap*", "xsmap"));
+        assert!(glob_match("Yealink SIP-T4*", "yealink sip-t46u 108.86.0.20"));
+        assert!(!glob_match("Yealink SIP-T4*", "Yealink SIP-T31"));
+        // Geri izleme: ilk `b` eşleşmesi yanlış olduğunda sonrakini denemeli.
+        assert!(glob_match("a*b*c", "aXbYbZc"));
+        assert!(!glob_match("a*b*c", "aXbYbZ"));
+        assert!(glob_match("*é*", "Téléphone"));
+    }
+
+    #[test]
+    fn user_agent_allow_list() {
+        let policy = RealmPolicy { allowed_user_agents: vec!["Yealink SIP-T4*".into()], ..Default::default() };
+        assert!(policy.check_user_agent(Some("Yealink SIP-T46U")).is_ok());
+        assert_eq!(policy.check_user_agent(None), Err(PolicyViolation::DeviceNotAllowed("-".into())));
+        assert!(policy.check_user_agent(Some("Zoiper")).is_err());
+        assert!(RealmPolicy::default().check_user_agent(None).is_ok());
+        let patterns = vec!["*sipvicious*".to_string(), "*friendly-scanner*".to_string()];
+        assert_eq!(match_user_agent(&patterns, "friendly-scanner"), Some("*friendly-scanner*"));
+    }
+
+    #[test]
+    fn instance_id_is_read_from_contact_params() {
+        assert_eq!(
+            instance_from_contact(r#"<sip:alice@10.0.0.5:5060>;+sip.instance="<urn:uuid:f81d4fae-7dec-11d0-a765-00a0c91e6bf6>";expires=3600"#),
+            Some("urn:uuid:f81d4fae-7dec-11d0-a765-00a0c91e6bf6".into())
+        );
+        assert_eq!(instance_from_contact("sip:alice@10.0.0.5;+SIP.INSTANCE=urn:uuid:abc"), Some("urn:uuid:abc".into()));
+        assert_eq!(instance_from_contact(r#"sip:alice@10.0.0.5;+sip.instance="""#), None);
+        assert_eq!(instance_from_contact("sip:alice@10.0.0.5;transport=tcp"), None);
+        assert_eq!(instance_from_contact("sip:alice@10.0.0.5"), None);
+    }
+}
@@ -0,0 +1,109 @@
+// src/reload.rs
+use crate::config::{self, AppConfig, SharedConfig};
+use crate::policy::PolicyStore;
+use crate::telemetry::{LogLevelController, LogRateLimiter};
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tracing::{error, info, warn};
+
+/// Dosya değişikliklerinde art arda gelen olayları tek bir reload'a indirgemek için bekleme.
+const WATCH_DEBOUNCE: Duration = Duration::from_millis(500);
+
+/// Yapılandırmanın çalışma anında değiştirilebilen kısımlarını (politikalar, log
+/// filtreleri, log hız sınırları, expiry sınırları) SIGHUP veya dosya değişikliğinde
+/// atomik olarak yeniler. Geçersiz yapılandırma reddedilir, eskisi korunur.
+#[derive(Clone)]
+pub struct ConfigReloader {
+    config: SharedConfig,
+    policies: PolicyStore,
+    log_limiter: LogRateLimiter,
+    log_level: LogLevelController,
+}
+
+impl ConfigReloader {
+    pub fn new(config: SharedConfig, policies: PolicyStore, log_limiter: LogRateLimiter, log_level: LogLevelController) -> Self {
+        Self { config, policies, log_limiter, log_level }
+    }
+
+    pub fn reload(&self, trigger: &str) {
+        let mut next = match AppConfig::load() {
+            Ok(next) => next,
+            Err(e) => {
+                error!(event="CONFIG_RELOAD_REJECTED", trigger=%trigger, error=%format!("{:#}", e), "Yeni yapılandırma geçersiz, mevcut yapılandırma korunuyor");
+                return;
+            }
+        };
+
+        let current = self.config.load_full();
+        if next.rust_log != current.rust_log {
+            if let Err(e) = self.log_level.set_baseline(&next.rust_log) {
+                error!(event="CONFIG_RELOAD_REJECTED", trigger=%trigger, error=%e, "Log direktifleri geçersiz, mevcut yapılandırma korunuyor");
+                return;
+            }
+        }
+
+        let ignored = current.restart_required_changes(&next);
+        if !ignored.is_empty() {
+            warn!(event="CONFIG_RELOAD_PARTIAL", fields=?ignored, "Bu alanlar yeniden başlatma gerektirir, şimdilik eski değerler kullanılıyor");
+            current.keep_restart_required(&mut next);
+        }
+
+        self.policies.set_base(next.policies.clone());
+        self.log_limiter.update(&next);
+        self.config.store(Arc::new(next));
+        info!(event="CONFIG_RELOADED", trigger=%trigger, "Yapılandırma çalışma anında yenilendi");
+    }
+
+    /// SIGHUP ve (varsa) yapılandırma dosyası değişikliklerini dinleyen görevi başlatır.
+    pub fn spawn(self) -> anyhow::Result<()> {
+        let (tx, mut rx) = mpsc::channel::<&'static str>(8);
+
+        #[cfg(unix)]
+        {
+            let tx = tx.clone();
+            let mut hangup = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::hangup())?;
+            tokio::spawn(async move {
+                while hangup.recv().await.is_some() {
+                    let _ = tx.send("sighup").await;
+                }
+            });
+        }
+
+        let watcher = match config::config_file_path() {
+            Some(path) => Some(watch_file(Path::new(&path), tx)?),
+            None => None,
+        };
+
+        tokio::spawn(async move {
+            // Watcher görev boyunca yaşamalı; düşerse dosya izleme durur.
+            let _watcher = watcher;
+            while let Some(trigger) = rx.recv().await {
+                if trigger == "file" {
+                    tokio::time::sleep(WATCH_DEBOUNCE).await;
+                    while rx.try_recv().is_ok() {}
+                }
+                self.reload(trigger);
+            }
+        });
+        Ok(())
+    }
+}
+
+/// Dosyanın bulunduğu dizini izler; Kubernetes ConfigMap'leri dosyayı symlink
+/// değişimiyle güncellediği için doğrudan dosyayı izlemek yeterli değildir.
+fn watch_file(path: &Path, tx: mpsc::Sender<&'static str>) -> anyhow::Result<RecommendedWatcher> {
+    let mut watcher = notify::recommended_watcher(move |res: notify::Result<notify::Event>| {
+        if let Ok(event) = res {
+            if event.kind.is_modify() || event.kind.is_create() || event.kind.is_remove() {
+                let _ = tx.try_send("file");
+            }
+        }
+    })?;
+    let dir = path.parent().filter(|p| !p.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    watcher.watch(dir, RecursiveMode::NonRecursive)?;
+    info!(event="CONFIG_WATCH_START", path=%path.display(), "Yapılandırma dosyası izleniyor");
+    Ok(watcher)
+}
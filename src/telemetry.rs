@@ -1,12 +1,165 @@
 // src/telemetry.rs
+use crate::config::AppConfig;
 use chrono::Utc;
+use opentelemetry::propagation::{Extractor, Injector};
+use opentelemetry::trace::{TraceContextExt, TracerProvider as _};
+use opentelemetry::{global, KeyValue};
+use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_sdk::export::trace::SpanData;
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use opentelemetry_sdk::trace::{self as sdktrace, BatchSpanProcessor, SpanProcessor, Tracer, TracerProvider};
+use opentelemetry_sdk::Resource;
 use serde::Serialize;
 use serde_json::{json, Value};
-use std::collections::HashMap;
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
 use std::fmt;
-use tracing::{Event, Subscriber};
+use arc_swap::ArcSwap;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tonic::metadata::{AsciiMetadataKey, AsciiMetadataValue, KeyRef, MetadataMap};
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Span, Subscriber};
+use tracing_opentelemetry::{OpenTelemetrySpanExt, OtelData};
+use tracing_subscriber::field::RecordFields;
 use tracing_subscriber::fmt::{format::Writer, FmtContext, FormatEvent, FormatFields};
+use tracing_subscriber::layer::{Context, Layer};
 use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::{reload, EnvFilter, Registry};
+
+/// Eski servislerin hâlâ okuduğu özel trace başlığı.
+const LEGACY_TRACE_HEADER: &str = "x-trace-id";
+
+/// W3C Trace Context propagator'ını kurar ve OpenTelemetry tracer'ını döner.
+/// OTLP endpoint tanımlıysa span'ler batch olarak collector'a gönderilir; değilse
+/// tracer yalnızca trace/span ID üretir ve span'ler dışarı aktarılmaz. Dışarı aktarılan
+/// span ve olay attribute'larına loglarla aynı PII politikası uygulanır.
+pub fn init_tracer(config: &AppConfig, redaction: RedactionPolicy) -> anyhow::Result<Tracer> {
+    global::set_text_map_propagator(TraceContextPropagator::new());
+
+    let trace_config = sdktrace::config().with_resource(Resource::new(vec![
+        KeyValue::new("service.name", "registrar-service"),
+        KeyValue::new("service.version", config.service_version.clone()),
+        KeyValue::new("deployment.environment", config.env.clone()),
+        KeyValue::new("host.name", config.node_hostname.clone()),
+    ]));
+
+    match &config.otlp_endpoint {
+        Some(endpoint) => {
+            let exporter = opentelemetry_otlp::new_exporter()
+                .tonic()
+                .with_endpoint(endpoint.clone())
+                .build_span_exporter()?;
+            let batch = BatchSpanProcessor::builder(exporter, opentelemetry_sdk::runtime::Tokio).build();
+            let provider = TracerProvider::builder()
+                .with_span_processor(RedactingSpanProcessor { inner: batch, redaction })
+                .with_config(trace_config)
+                .build();
+            let tracer = provider.tracer("registrar-service");
+            let _ = global::set_tracer_provider(provider);
+            Ok(tracer)
+        }
+        None => {
+            let provider = TracerProvider::builder().with_config(trace_config).build();
+            let tracer = provider.tracer("registrar-service");
+            let _ = global::set_tracer_provider(provider);
+            Ok(tracer)
+        }
+    }
+}
+
+/// Span'i dışarı aktarmadan önce PII attribute'larını gizleyen sarmalayıcı.
+#[derive(Debug)]
+struct RedactingSpanProcessor<P> {
+    inner: P,
+    redaction: RedactionPolicy,
+}
+
+impl<P: SpanProcessor> SpanProcessor for RedactingSpanProcessor<P> {
+    fn on_start(&self, span: &mut sdktrace::Span, cx: &opentelemetry::Context) {
+        self.inner.on_start(span, cx);
+    }
+
+    fn on_end(&self, mut span: SpanData) {
+        self.redaction.apply_otel(&mut span.attributes);
+        for event in span.events.events.iter_mut() {
+            self.redaction.apply_otel(&mut event.attributes);
+        }
+        self.inner.on_end(span);
+    }
+
+    fn force_flush(&self) -> opentelemetry::trace::TraceResult<()> {
+        self.inner.force_flush()
+    }
+
+    fn shutdown(&mut self) -> opentelemetry::trace::TraceResult<()> {
+        self.inner.shutdown()
+    }
+}
+
+/// Bekleyen span'leri collector'a boşaltır.
+pub fn shutdown_tracer() {
+    global::shutdown_tracer_provider();
+}
+
+/// Gelen isteğin `traceparent`/`tracestate` başlıklarını çözer ve aktif span'e
+/// ebeveyn bağlam olarak bağlar. Loglarda kullanılacak trace ID'yi döner.
+pub fn attach_remote_context(metadata: &MetadataMap) -> String {
+    let parent_cx = global::get_text_map_propagator(|p| p.extract(&MetadataExtractor(metadata)));
+    let remote = parent_cx.span().span_context().clone();
+
+    if remote.is_valid() {
+        Span::current().set_parent(parent_cx);
+        return remote.trace_id().to_string();
+    }
+
+    // W3C bağlamı yoksa eski başlığa düş.
+    metadata.get(LEGACY_TRACE_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("unknown")
+        .to_string()
+}
+
+/// Aktif span'in bağlamını giden gRPC isteğinin metadata'sına yazar.
+pub fn inject_current_context(metadata: &mut MetadataMap) {
+    let cx = Span::current().context();
+    global::get_text_map_propagator(|p| p.inject_context(&cx, &mut MetadataInjector(metadata)));
+
+    let span_context = cx.span().span_context().clone();
+    if span_context.is_valid() {
+        if let Ok(v) = span_context.trace_id().to_string().parse::<AsciiMetadataValue>() {
+            metadata.insert(LEGACY_TRACE_HEADER, v);
+        }
+    }
+}
+
+struct MetadataExtractor<'a>(&'a MetadataMap);
+
+impl Extractor for MetadataExtractor<'_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).and_then(|v| v.to_str().ok())
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0.keys()
+            .map(|k| match k {
+                KeyRef::Ascii(k) => k.as_str(),
+                KeyRef::Binary(k) => k.as_str(),
+            })
+            .collect()
+    }
+}
+
+struct MetadataInjector<'a>(&'a mut MetadataMap);
+
+impl Injector for MetadataInjector<'_> {
+    fn set(&mut self, key: &str, value: String) {
+        if let (Ok(k), Ok(v)) = (AsciiMetadataKey::from_bytes(key.as_bytes()), value.parse::<AsciiMetadataValue>()) {
+            self.0.insert(k, v);
+        }
+    }
+}
 
 /// SUTS v4.0 Log Record (Registrar Edition)
 #[derive(Serialize)]
@@ -49,10 +202,12 @@ struct ResourceContext {
 
 pub struct SutsFormatter {
     resource: ResourceContext,
+    default_tenant_id: String,
+    redaction: RedactionPolicy,
 }
 
 impl SutsFormatter {
-    pub fn new(service_name: String, version: String, env: String, host_name: String) -> Self {
+    pub fn new(service_name: String, version: String, env: String, host_name: String, default_tenant_id: String) -> Self {
         Self {
             resource: ResourceContext {
                 service_name,
@@ -60,6 +215,191 @@ impl SutsFormatter {
                 service_env: env,
                 host_name,
             },
+            default_tenant_id,
+            redaction: RedactionPolicy::disabled(),
+        }
+    }
+
+    pub fn with_redaction(mut self, redaction: RedactionPolicy) -> Self {
+        self.redaction = redaction;
+        self
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RedactionMode {
+    Off,
+    /// Değer tamamen gizlenir.
+    Mask,
+    /// Değer tuzlu SHA-256 özetiyle değiştirilir; aynı değer her satırda aynı özeti
+    /// ürettiği için loglar arası korelasyon korunur.
+    Hash,
+}
+
+/// Log attribute'larındaki kişisel verilerin (SIP URI, kullanıcı adı, contact IP:port)
+/// ortam bazlı gizleme politikası. JSON ve metin loglarına, span alanlarına ve OTLP'ye
+/// aktarılan span attribute'larına aynı şekilde uygulanır.
+#[derive(Debug, Clone)]
+pub struct RedactionPolicy {
+    mode: RedactionMode,
+    keys: HashSet<String>,
+    salt: String,
+}
+
+impl RedactionPolicy {
+    pub fn disabled() -> Self {
+        Self { mode: RedactionMode::Off, keys: HashSet::new(), salt: String::new() }
+    }
+
+    pub fn from_config(config: &AppConfig) -> anyhow::Result<Self> {
+        let mode = match config.log_pii_mode.as_str() {
+            "off" => RedactionMode::Off,
+            "mask" => RedactionMode::Mask,
+            "hash" => RedactionMode::Hash,
+            other => anyhow::bail!("Geçersiz LOG_PII_MODE: {} (off|mask|hash)", other),
+        };
+        Ok(Self {
+            mode,
+            keys: config.log_pii_keys.iter().cloned().collect(),
+            salt: config.log_pii_salt.clone(),
+        })
+    }
+
+    pub fn mode(&self) -> RedactionMode {
+        self.mode
+    }
+
+    pub fn redact(&self, raw: &str) -> String {
+        match self.mode {
+            RedactionMode::Off => raw.to_string(),
+            RedactionMode::Mask => "***".to_string(),
+            RedactionMode::Hash => {
+                let mut hasher = Sha256::new();
+                hasher.update(self.salt.as_bytes());
+                hasher.update(b":");
+                hasher.update(raw.as_bytes());
+                let digest = hasher.finalize();
+                let short: String = digest[..8].iter().map(|b| format!("{:02x}", b)).collect();
+                format!("h:{}", short)
+            }
+        }
+    }
+
+    fn applies_to(&self, key: &str) -> bool {
+        self.mode != RedactionMode::Off && self.keys.contains(key)
+    }
+
+    fn apply(&self, attributes: &mut HashMap<String, Value>) {
+        for (key, value) in attributes.iter_mut() {
+            if !self.applies_to(key) {
+                continue;
+            }
+            let raw = match value {
+                Value::String(s) => s.clone(),
+                Value::Null => continue,
+                other => other.to_string(),
+            };
+            *value = Value::String(self.redact(&raw));
+        }
+    }
+
+    fn apply_otel(&self, attributes: &mut [KeyValue]) {
+        for kv in attributes.iter_mut() {
+            if self.applies_to(kv.key.as_str()) {
+                kv.value = self.redact(&kv.value.as_str()).into();
+            }
+        }
+    }
+}
+
+/// Metin (`compact`) log biçimi için alan biçimlendirici; PII alanlarını `RedactionPolicy`
+/// ile gizler. Span alanları da bu biçimlendiriciden geçer.
+pub struct RedactingFields {
+    redaction: RedactionPolicy,
+}
+
+impl RedactingFields {
+    pub fn new(redaction: RedactionPolicy) -> Self {
+        Self { redaction }
+    }
+}
+
+impl<'writer> FormatFields<'writer> for RedactingFields {
+    fn format_fields<R: RecordFields>(&self, mut writer: Writer<'writer>, fields: R) -> fmt::Result {
+        let mut visitor = TextVisitor::default();
+        fields.record(&mut visitor);
+
+        let mut first = true;
+        for (key, value) in visitor.fields {
+            if !first {
+                writer.write_char(' ')?;
+            }
+            first = false;
+            if key == "message" {
+                write!(writer, "{}", value)?;
+            } else if self.redaction.applies_to(key) {
+                write!(writer, "{}={}", key, self.redaction.redact(&value))?;
+            } else {
+                write!(writer, "{}={}", key, value)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Alanları kayıt sırasıyla toplar (metin biçimi için).
+#[derive(Default)]
+struct TextVisitor {
+    fields: Vec<(&'static str, String)>,
+}
+
+impl tracing::field::Visit for TextVisitor {
+    fn record_debug(&mut self, field: &tracing::field::Field, value: &dyn fmt::Debug) {
+        self.fields.push((field.name(), format!("{:?}", value)));
+    }
+    fn record_str(&mut self, field: &tracing::field::Field, value: &str) {
+        self.fields.push((field.name(), value.to_string()));
+    }
+}
+
+/// Span alanlarının JSON karşılığı; `SpanFieldsLayer` tarafından span extension'ına yazılır.
+struct SpanFields(HashMap<String, Value>);
+
+/// `#[instrument]` ile kaydedilen span alanlarını (sip.uri, trace_id, tenant.id ...)
+/// yapısal olarak saklar, böylece `SutsFormatter` bunları log kaydına katabilir.
+/// PII alanları saklanmadan önce gizlenir.
+pub struct SpanFieldsLayer {
+    redaction: RedactionPolicy,
+}
+
+impl SpanFieldsLayer {
+    pub fn new(redaction: RedactionPolicy) -> Self {
+        Self { redaction }
+    }
+}
+
+impl<S> Layer<S> for SpanFieldsLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        if let Some(span) = ctx.span(id) {
+            let mut visitor = JsonVisitor::default();
+            attrs.record(&mut visitor);
+            self.redaction.apply(&mut visitor.fields);
+            span.extensions_mut().insert(SpanFields(visitor.fields));
+        }
+    }
+
+    fn on_record(&self, id: &Id, values: &Record<'_>, ctx: Context<'_, S>) {
+        if let Some(span) = ctx.span(id) {
+            let mut ext = span.extensions_mut();
+            if let Some(span_fields) = ext.get_mut::<SpanFields>() {
+                let mut visitor = JsonVisitor::default();
+                values.record(&mut visitor);
+                self.redaction.apply(&mut visitor.fields);
+                span_fields.0.extend(visitor.fields);
+            }
         }
     }
 }
@@ -71,7 +411,7 @@ where
 {
     fn format_event(
         &self,
-        _ctx: &FmtContext<'_, S, N>,
+        ctx: &FmtContext<'_, S, N>,
         mut writer: Writer<'_>,
         event: &Event<'_>,
     ) -> fmt::Result {
@@ -89,33 +429,70 @@ where
         let mut visitor = JsonVisitor::default();
         event.record(&mut visitor);
 
-        let event_name = visitor.fields.remove("event")
+        // Span hiyerarşisindeki alanlar kökten yaprağa birleştirilir; olay alanları en son yazılır.
+        let mut attributes = HashMap::new();
+        if let Some(scope) = ctx.event_scope() {
+            for span in scope.from_root() {
+                if let Some(span_fields) = span.extensions().get::<SpanFields>() {
+                    attributes.extend(span_fields.0.iter().map(|(k, v)| (k.clone(), v.clone())));
+                }
+            }
+        }
+        attributes.extend(visitor.fields);
+
+        let event_name = attributes.remove("event")
             .and_then(|v| v.as_str().map(|s| s.to_string()))
             .unwrap_or_else(|| "LOG_EVENT".to_string());
 
-        let message = visitor.fields.remove("message")
+        let message = attributes.remove("message")
             .and_then(|v| v.as_str().map(|s| s.to_string()))
             .unwrap_or_else(String::new);
 
-        let trace_id = if let Some(tid) = visitor.fields.get("trace_id").and_then(|v| v.as_str()) {
+        // Aktif span'in OpenTelemetry verisi varsa gerçek trace/span ID'leri oradan gelir.
+        let (otel_trace_id, span_id) = ctx.lookup_current()
+            .and_then(|span| {
+                let ext = span.extensions();
+                let ids = ext.get::<OtelData>().map(|data| {
+                    let trace_id = if data.parent_cx.has_active_span() {
+                        Some(data.parent_cx.span().span_context().trace_id())
+                    } else {
+                        data.builder.trace_id
+                    };
+                    (trace_id.map(|t| t.to_string()), data.builder.span_id.map(|s| s.to_string()))
+                });
+                ids
+            })
+            .unwrap_or((None, None));
+
+        let trace_id = if otel_trace_id.is_some() {
+            otel_trace_id
+        } else if let Some(tid) = attributes.get("trace_id").and_then(|v| v.as_str()) {
             Some(tid.to_string())
-        } else if let Some(cid) = visitor.fields.get("sip.call_id").and_then(|v| v.as_str()) {
+        } else if let Some(cid) = attributes.get("sip.call_id").and_then(|v| v.as_str()) {
             Some(cid.to_string())
         } else {
             None
         };
 
+        let tenant_id = attributes.remove("tenant.id")
+            .or_else(|| attributes.remove("tenant_id"))
+            .and_then(|v| v.as_str().map(|s| s.to_string()))
+            .filter(|t| !t.is_empty())
+            .unwrap_or_else(|| self.default_tenant_id.clone());
+
+        self.redaction.apply(&mut attributes);
+
         let log_record = SutsLogRecord {
             schema_v: "1.0.0",
             ts,
             severity,
-            tenant_id: "sentiric_demo".to_string(), 
+            tenant_id,
             resource: self.resource.clone(),
             trace_id,
-            span_id: None,
+            span_id,
             event: event_name,
             message,
-            attributes: visitor.fields,
+            attributes,
             _marker: std::marker::PhantomData,
         };
 
@@ -154,4 +531,362 @@ impl tracing::field::Visit for JsonVisitor {
     fn record_error(&mut self, field: &tracing::field::Field, value: &(dyn std::error::Error + 'static)) {
         self.fields.insert(field.name().to_string(), Value::String(value.to_string()));
     }
-}
\ No newline at end of file
+}
+
+/// Bastırılan satırların özet olayının adı; kendisi limitlere tabi değildir.
+const LOG_SUPPRESSED_EVENT: &str = "LOG_SUPPRESSED";
+
+/// Olay adına göre örnekleme (1/N) ve token-bucket hız sınırı uygulayan katman.
+/// Bastırılan satırlar sayılır ve `report_suppressed` ile periyodik olarak özetlenir.
+/// ERROR seviyesindeki, `audit = true` taşıyan ve `SECURITY_` önekli olaylar hiçbir
+/// zaman bastırılmaz; saldırı sırasında güvenlik kaydı kaybolmaz.
+///
+/// Sıcak yolda kilit yoktur: olay başına durum atomik sayaçlardır, olay tablosu
+/// yalnızca yeni bir olay adı ilk kez görüldüğünde kopyalanarak genişletilir.
+#[derive(Clone)]
+pub struct LogRateLimiter {
+    events: Arc<ArcSwap<HashMap<String, Arc<EventState>>>>,
+    insert_lock: Arc<Mutex<()>>,
+    settings: Arc<ArcSwap<LimiterSettings>>,
+    epoch: Instant,
+}
+
+struct LimiterSettings {
+    sample_every: HashMap<String, u64>,
+    rate_per_sec: f64,
+    burst: f64,
+}
+
+impl LimiterSettings {
+    fn from_config(config: &AppConfig) -> Self {
+        Self {
+            sample_every: config
+                .log_sample_every
+                .iter()
+                .map(|(event, every)| (event.to_ascii_uppercase(), *every))
+                .collect(),
+            rate_per_sec: config.log_rate_limit_per_sec as f64,
+            burst: config.log_rate_limit_burst.max(1) as f64,
+        }
+    }
+
+    /// Anahtarlar büyük harfle saklanır; olay adı da aynı biçimde aranır.
+    fn sample_rate(&self, event_name: &str) -> Option<u64> {
+        if self.sample_every.is_empty() {
+            return None;
+        }
+        match event_name.bytes().any(|b| b.is_ascii_lowercase()) {
+            true => self.sample_every.get(&event_name.to_ascii_uppercase()).copied(),
+            false => self.sample_every.get(event_name).copied(),
+        }
+    }
+}
+
+/// Olay başına sayaçlar. Hız sınırı GCRA ile tek bir atomik "teorik varış zamanı"
+/// (epoch'tan nanosaniye) üzerinde uygulanır; `burst` kapasiteli token-bucket'a eşdeğerdir.
+#[derive(Default)]
+struct EventState {
+    seen: AtomicU64,
+    suppressed: AtomicU64,
+    tat_nanos: AtomicU64,
+}
+
+impl EventState {
+    fn take_token(&self, now: u64, rate_per_sec: f64, burst: f64) -> bool {
+        let interval = (1e9 / rate_per_sec).max(1.0) as u64;
+        let tolerance = interval.saturating_mul((burst - 1.0).max(0.0) as u64);
+        let mut tat = self.tat_nanos.load(Ordering::Relaxed);
+        loop {
+            let start = tat.max(now);
+            if start - now > tolerance {
+                return false;
+            }
+            match self.tat_nanos.compare_exchange_weak(tat, start + interval, Ordering::AcqRel, Ordering::Relaxed) {
+                Ok(_) => return true,
+                Err(current) => tat = current,
+            }
+        }
+    }
+}
+
+impl LogRateLimiter {
+    pub fn from_config(config: &AppConfig) -> Self {
+        Self {
+            events: Arc::new(ArcSwap::from_pointee(HashMap::new())),
+            insert_lock: Arc::new(Mutex::new(())),
+            settings: Arc::new(ArcSwap::from_pointee(LimiterSettings::from_config(config))),
+            epoch: Instant::now(),
+        }
+    }
+
+    /// Örnekleme ve hız sınırı ayarlarını yeniler; mevcut sayaçlar korunur.
+    pub fn update(&self, config: &AppConfig) {
+        self.settings.store(Arc::new(LimiterSettings::from_config(config)));
+    }
+
+    fn state(&self, event_name: &str) -> Arc<EventState> {
+        if let Some(state) = self.events.load().get(event_name) {
+            return state.clone();
+        }
+        let _guard = self.insert_lock.lock();
+        let current = self.events.load();
+        if let Some(state) = current.get(event_name) {
+            return state.clone();
+        }
+        let state = Arc::new(EventState::default());
+        let mut next = (**current).clone();
+        next.insert(event_name.to_string(), state.clone());
+        self.events.store(Arc::new(next));
+        state
+    }
+
+    fn admit(&self, event_name: &str) -> bool {
+        let settings = self.settings.load();
+        let state = self.state(event_name);
+
+        if let Some(every) = settings.sample_rate(event_name) {
+            let seen = state.seen.fetch_add(1, Ordering::Relaxed);
+            if every > 1 && !seen.is_multiple_of(every) {
+                state.suppressed.fetch_add(1, Ordering::Relaxed);
+                return false;
+            }
+        }
+
+        if settings.rate_per_sec <= 0.0 {
+            return true;
+        }
+
+        let now = self.epoch.elapsed().as_nanos() as u64;
+        if state.take_token(now, settings.rate_per_sec, settings.burst) {
+            true
+        } else {
+            state.suppressed.fetch_add(1, Ordering::Relaxed);
+            false
+        }
+    }
+
+    /// Son rapordan beri bastırılan satırları olay adı başına tek bir özet olayla loglar.
+    pub fn report_suppressed(&self) {
+        for (event_name, state) in self.events.load().iter() {
+            let count = state.suppressed.swap(0, Ordering::Relaxed);
+            if count == 0 {
+                continue;
+            }
+            tracing::warn!(
+                event = LOG_SUPPRESSED_EVENT,
+                suppressed_event = %event_name,
+                count = count,
+                "{} adet log satırı örnekleme/hız sınırı nedeniyle bastırıldı", count
+            );
+        }
+    }
+}
+
+impl<S> Layer<S> for LogRateLimiter
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn event_enabled(&self, event: &Event<'_>, _ctx: Context<'_, S>) -> bool {
+        if *event.metadata().level() == tracing::Level::ERROR {
+            return true;
+        }
+
+        let mut visitor = EventNameVisitor::default();
+        event.record(&mut visitor);
+        if visitor.audit {
+            return true;
+        }
+        let event_name = visitor.name.unwrap_or_else(|| "LOG_EVENT".to_string());
+        if event_name == LOG_SUPPRESSED_EVENT || event_name.starts_with("SECURITY_") {
+            return true;
+        }
+
+        self.admit(&event_name)
+    }
+}
+
+#[derive(Default)]
+struct EventNameVisitor {
+    name: Option<String>,
+    audit: bool,
+}
+
+impl tracing::field::Visit for EventNameVisitor {
+    fn record_str(&mut self, field: &tracing::field::Field, value: &str) {
+        if field.name() == "event" {
+            self.name = Some(value.to_string());
+        }
+    }
+    fn record_bool(&mut self, field: &tracing::field::Field, value: bool) {
+        if field.name() == "audit" {
+            self.audit = value;
+        }
+    }
+    fn record_debug(&mut self, field: &tracing::field::Field, value: &dyn fmt::Debug) {
+        if field.name() == "event" {
+            self.name = Some(format!("{:?}", value).trim_matches('"').to_string());
+        }
+    }
+}
+
+pub type LogFilterHandle = reload::Handle<EnvFilter, Registry>;
+
+/// Çalışma anında `EnvFilter` direktiflerini değiştirir. TTL verilirse süre dolunca
+/// temel (baseline) direktiflere otomatik olarak geri döner.
+#[derive(Clone)]
+pub struct LogLevelController {
+    handle: LogFilterHandle,
+    baseline: Arc<ArcSwap<String>>,
+    overridden: Arc<AtomicBool>,
+    revert_task: Arc<Mutex<Option<tokio::task::JoinHandle<()>>>>,
+}
+
+impl LogLevelController {
+    pub fn new(handle: LogFilterHandle, baseline: String) -> Self {
+        Self {
+            handle,
+            baseline: Arc::new(ArcSwap::from_pointee(baseline)),
+            overridden: Arc::new(AtomicBool::new(false)),
+            revert_task: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    pub fn current(&self) -> String {
+        self.handle.with_current(|filter| filter.to_string()).unwrap_or_default()
+    }
+
+    pub fn baseline(&self) -> String {
+        self.baseline.load().to_string()
+    }
+
+    /// Yapılandırma yeniden yüklendiğinde temel direktifleri değiştirir. Admin API ile
+    /// yapılmış geçici bir değişiklik aktifse o korunur; yeni temel TTL sonunda devreye girer.
+    pub fn set_baseline(&self, directives: &str) -> anyhow::Result<()> {
+        let filter = EnvFilter::try_new(directives)?;
+        self.baseline.store(Arc::new(directives.to_string()));
+        if !self.overridden.load(Ordering::SeqCst) {
+            self.handle.reload(filter)?;
+        }
+        Ok(())
+    }
+
+    pub fn apply(&self, directives: &str, ttl: Option<Duration>) -> anyhow::Result<()> {
+        let filter = EnvFilter::try_new(directives)?;
+        self.handle.reload(filter)?;
+        self.overridden.store(true, Ordering::SeqCst);
+        self.cancel_revert();
+
+        if let Some(ttl) = ttl {
+            let this = self.clone();
+            let task = tokio::spawn(async move {
+                tokio::time::sleep(ttl).await;
+                if let Err(e) = this.restore_baseline() {
+                    tracing::error!(event="LOG_LEVEL_REVERT_FAIL", error=%e, "Log seviyesi geri alınamadı");
+                } else {
+                    tracing::info!(event="LOG_LEVEL_REVERTED", directives=%this.baseline(), "Log seviyesi TTL sonunda geri alındı");
+                }
+            });
+            if let Ok(mut slot) = self.revert_task.lock() {
+                *slot = Some(task);
+            }
+        }
+
+        tracing::info!(
+            event = "LOG_LEVEL_CHANGED",
+            directives = %directives,
+            ttl_secs = ttl.map(|t| t.as_secs()),
+            "Log seviyesi çalışma anında değiştirildi"
+        );
+        Ok(())
+    }
+
+    pub fn reset(&self) -> anyhow::Result<()> {
+        self.cancel_revert();
+        self.restore_baseline()?;
+        tracing::info!(event="LOG_LEVEL_RESET", directives=%self.baseline(), "Log seviyesi başlangıç değerine döndü");
+        Ok(())
+    }
+
+    fn restore_baseline(&self) -> anyhow::Result<()> {
+        self.handle.reload(EnvFilter::try_new(self.baseline.load().as_str())?)?;
+        self.overridden.store(false, Ordering::SeqCst);
+        Ok(())
+    }
+
+    fn cancel_revert(&self) {
+        if let Some(task) = self.revert_task.lock().ok().and_then(|mut slot| slot.take()) {
+            task.abort();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gcra_admits_burst_then_refills_at_rate() {
+        let state = EventState::default();
+        // 10/sn, kapasite 3: ilk 3 geçer, 4. reddedilir.
+        assert!((0..3).all(|_| state.take_token(0, 10.0, 3.0)));
+        assert!(!state.take_token(0, 10.0, 3.0));
+        // 100ms sonra bir jeton dolar.
+        assert!(state.take_token(100_000_000, 10.0, 3.0));
+        assert!(!state.take_token(100_000_000, 10.0, 3.0));
+    }
+
+    #[test]
+    fn gcra_with_burst_one_allows_single_event_per_interval() {
+        let state = EventState::default();
+        assert!(state.take_token(0, 1.0, 1.0));
+        assert!(!state.take_token(500_000_000, 1.0, 1.0));
+        assert!(state.take_token(1_000_000_000, 1.0, 1.0));
+    }
+
+    fn policy(mode: &str, salt: &str) -> RedactionPolicy {
+        RedactionPolicy::from_config(&AppConfig { log_pii_mode: mode.into(), log_pii_salt: salt.into(), ..AppConfig::default() }).unwrap()
+    }
+
+    #[test]
+    fn hash_is_salted_stable_and_short() {
+        let a = policy("hash", "salt-a");
+        let hashed = a.redact("905551112233");
+        assert!(hashed.starts_with("h:") && hashed.len() == 18 && hashed[2..].chars().all(|c| c.is_ascii_hexdigit()));
+        // Aynı değer aynı özeti verir (korelasyon); farklı tuz farklı özet verir.
+        assert_eq!(a.redact("905551112233"), hashed);
+        assert_ne!(policy("hash", "salt-b").redact("905551112233"), hashed);
+        assert_ne!(a.redact("905551112234"), hashed);
+    }
+
+    #[test]
+    fn mask_and_off_modes() {
+        assert_eq!(policy("mask", "").redact("sip:1001@acme.example"), "***");
+        assert_eq!(policy("off", "").redact("sip:1001@acme.example"), "sip:1001@acme.example");
+        assert!(RedactionPolicy::from_config(&AppConfig { log_pii_mode: "auto".into(), ..AppConfig::default() }).is_err());
+    }
+
+    #[test]
+    fn only_configured_keys_are_redacted() {
+        let mask = policy("mask", "");
+        let mut attributes: HashMap<String, Value> = [
+            ("sip.user", json!("1001")),
+            ("contact", json!({ "host": "10.0.0.5" })),
+            ("uri", Value::Null),
+            ("event", json!("SIP_REGISTER_SUCCESS")),
+        ]
+        .into_iter()
+        .map(|(k, v)| (k.to_string(), v))
+        .collect();
+        mask.apply(&mut attributes);
+        assert_eq!(attributes["sip.user"], json!("***"));
+        assert_eq!(attributes["contact"], json!("***"));
+        assert_eq!(attributes["uri"], Value::Null);
+        assert_eq!(attributes["event"], json!("SIP_REGISTER_SUCCESS"));
+
+        let hash = policy("hash", "s");
+        let mut otel = [KeyValue::new("sip.uri", "sip:1001@acme.example"), KeyValue::new("trace_id", "abc")];
+        hash.apply_otel(&mut otel);
+        assert_eq!(otel[0].value.as_str(), hash.redact("sip:1001@acme.example"));
+        assert_eq!(otel[1].value.as_str(), "abc");
+    }
+}
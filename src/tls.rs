@@ -1,22 +1,334 @@
 // sentiric-registrar-service/src/tls.rs
-use crate::config::AppConfig;
+use crate::config::{self, AppConfig, SharedConfig};
+use crate::metrics;
 use anyhow::{Context, Result};
+use arc_swap::ArcSwap;
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
 use tokio::fs;
-use tonic::transport::{Certificate, Identity, ServerTlsConfig};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::{mpsc, watch};
+use tokio_rustls::rustls::server::WebPkiClientVerifier;
+use tokio_rustls::rustls::{RootCertStore, ServerConfig};
+use tokio_rustls::server::TlsStream;
+use tokio_rustls::TlsAcceptor;
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::transport::{Certificate, ClientTlsConfig, Identity};
+use tracing::{debug, error, info, warn};
+use x509_parser::extensions::GeneralName;
 
-pub async fn load_server_tls_config(config: &AppConfig) -> Result<ServerTlsConfig> {
-    let identity = {
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+const WATCH_DEBOUNCE: Duration = Duration::from_millis(500);
+
+/// Diskten okunmuş sertifika, anahtar ve CA (PEM).
+pub struct TlsMaterial {
+    pub cert: Vec<u8>,
+    pub key: Vec<u8>,
+    pub ca: Vec<u8>,
+    /// İstemci sertifikaları için iptal listesi (PEM, opsiyonel).
+    pub crl: Option<Vec<u8>>,
+    /// Sunucu/istemci sertifikasının bitiş zamanı (unix saniye).
+    pub not_after: i64,
+}
+
+impl TlsMaterial {
+    pub async fn load(config: &AppConfig) -> Result<Self> {
         let cert = fs::read(&config.cert_path).await.context("Sunucu sertifikası okunamadı")?;
         let key = fs::read(&config.key_path).await.context("Sunucu anahtarı okunamadı")?;
-        Identity::from_pem(cert, key)
-    };
-    
-    let client_ca_cert = {
         let ca = fs::read(&config.ca_path).await.context("CA sertifikası okunamadı")?;
-        Certificate::from_pem(ca)
+        let crl = match &config.crl_path {
+            Some(path) => Some(fs::read(path).await.context("CRL dosyası okunamadı")?),
+            None => None,
+        };
+        let not_after = pem_not_after(&cert).context("Sunucu sertifikası ayrıştırılamadı")?;
+        Ok(Self { cert, key, ca, crl, not_after })
+    }
+
+    fn server_config(&self) -> Result<ServerConfig> {
+        let certs = rustls_pemfile::certs(&mut self.cert.as_slice())
+            .collect::<Result<Vec<_>, _>>()
+            .context("Sunucu sertifikası PEM hatası")?;
+        let key = rustls_pemfile::private_key(&mut self.key.as_slice())
+            .context("Sunucu anahtarı PEM hatası")?
+            .context("Sunucu anahtarı bulunamadı")?;
+
+        let mut roots = RootCertStore::empty();
+        for ca in rustls_pemfile::certs(&mut self.ca.as_slice()) {
+            roots.add(ca.context("CA PEM hatası")?).context("CA sertifikası eklenemedi")?;
+        }
+        let mut verifier = WebPkiClientVerifier::builder(Arc::new(roots));
+        if let Some(crl) = &self.crl {
+            let crls = rustls_pemfile::crls(&mut crl.as_slice())
+                .collect::<Result<Vec<_>, _>>()
+                .context("CRL PEM hatası")?;
+            verifier = verifier.with_crls(crls).only_check_end_entity_revocation();
+        }
+        let verifier = verifier
+            .build()
+            .context("İstemci sertifika doğrulayıcısı oluşturulamadı")?;
+
+        let mut server_config = ServerConfig::builder()
+            .with_client_cert_verifier(verifier)
+            .with_single_cert(certs, key)
+            .context("Sunucu TLS yapılandırması oluşturulamadı")?;
+        server_config.alpn_protocols = vec![b"h2".to_vec()];
+        Ok(server_config)
+    }
+
+    pub fn client_tls_config(&self, server_name: &str) -> ClientTlsConfig {
+        ClientTlsConfig::new()
+            .domain_name(server_name)
+            .ca_certificate(Certificate::from_pem(self.ca.clone()))
+            .identity(Identity::from_pem(self.cert.clone(), self.key.clone()))
+    }
+}
+
+/// PEM içindeki ilk sertifikanın `notAfter` değeri (unix saniye).
+fn pem_not_after(pem: &[u8]) -> Result<i64> {
+    let der = rustls_pemfile::certs(&mut &pem[..])
+        .next()
+        .context("PEM içinde sertifika yok")??;
+    let (_, cert) = x509_parser::parse_x509_certificate(der.as_ref())
+        .map_err(|e| anyhow::anyhow!("X.509 ayrıştırma hatası: {}", e))?;
+    Ok(cert.validity().not_after.timestamp())
+}
+
+/// Bağlanan istemcinin sertifikasından çıkarılan kimlik bilgileri.
+pub struct PeerCert {
+    /// DNS/URI SAN değerleri ve subject CN.
+    pub identities: Vec<String>,
+    /// Seri numarası ve DER üzerinden SHA-256 parmak izi; `normalize_cert_id` biçiminde.
+    pub serial: String,
+    pub fingerprint: String,
+    pub not_after: i64,
+}
+
+impl PeerCert {
+    pub fn parse(der: &[u8]) -> Option<Self> {
+        let (_, cert) = x509_parser::parse_x509_certificate(der).ok()?;
+
+        let mut identities: Vec<String> = Vec::new();
+        if let Ok(Some(san)) = cert.subject_alternative_name() {
+            for name in &san.value.general_names {
+                match name {
+                    GeneralName::DNSName(v) | GeneralName::URI(v) => identities.push(v.to_string()),
+                    _ => {}
+                }
+            }
+        }
+        identities.extend(
+            cert.subject()
+                .iter_common_name()
+                .filter_map(|cn| cn.as_str().ok())
+                .map(str::to_string),
+        );
+
+        let serial: String = cert.raw_serial().iter().map(|b| format!("{:02x}", b)).collect();
+        let fingerprint: String = Sha256::digest(der).iter().map(|b| format!("{:02x}", b)).collect();
+        Some(Self {
+            identities,
+            serial: config::normalize_cert_id(&serial),
+            fingerprint: config::normalize_cert_id(&fingerprint),
+            not_after: cert.validity().not_after.timestamp(),
+        })
+    }
+
+    pub fn primary_identity(&self) -> &str {
+        self.identities.first().map(String::as_str).unwrap_or("unknown")
+    }
+}
+
+/// Sunucu ve istemci tarafının paylaştığı, çalışma anında yenilenebilen TLS durumu.
+/// Yeni materyal yalnızca yeni bağlantılara uygulanır; mevcut bağlantılar etkilenmez.
+#[derive(Clone)]
+pub struct TlsState {
+    config: Arc<AppConfig>,
+    material: Arc<ArcSwap<TlsMaterial>>,
+    server_config: Arc<ArcSwap<ServerConfig>>,
+    generation: Arc<watch::Sender<u64>>,
+}
+
+impl TlsState {
+    pub async fn load(config: Arc<AppConfig>) -> Result<Self> {
+        let material = TlsMaterial::load(&config).await?;
+        let server_config = material.server_config()?;
+        record_expiry(&material);
+        let (generation, _) = watch::channel(0);
+        Ok(Self {
+            config,
+            material: Arc::new(ArcSwap::from_pointee(material)),
+            server_config: Arc::new(ArcSwap::from_pointee(server_config)),
+            generation: Arc::new(generation),
+        })
+    }
+
+    pub fn material(&self) -> Arc<TlsMaterial> {
+        self.material.load_full()
+    }
+
+    /// Her başarılı rotasyonda değişen sayaç; istemci kanalları bunu dinleyerek yeniden kurulur.
+    pub fn subscribe(&self) -> watch::Receiver<u64> {
+        self.generation.subscribe()
+    }
+
+    /// Dosyaları yeniden okur. Geçersiz materyal reddedilir ve eskisi kullanılmaya devam eder.
+    pub async fn reload(&self) -> Result<()> {
+        let result = async {
+            let material = TlsMaterial::load(&self.config).await?;
+            let server_config = material.server_config()?;
+            Ok::<_, anyhow::Error>((material, server_config))
+        }.await;
+
+        match result {
+            Ok((material, server_config)) => {
+                record_expiry(&material);
+                info!(
+                    event = "TLS_CERT_ROTATED",
+                    not_after = %chrono::DateTime::from_timestamp(material.not_after, 0).map(|t| t.to_rfc3339()).unwrap_or_default(),
+                    "TLS sertifikaları yenilendi"
+                );
+                self.server_config.store(Arc::new(server_config));
+                self.material.store(Arc::new(material));
+                self.generation.send_modify(|g| *g += 1);
+                metrics::inc_counter("registrar_tls_reload_total", &[("result", "success")]);
+                Ok(())
+            }
+            Err(e) => {
+                metrics::inc_counter("registrar_tls_reload_total", &[("result", "failure")]);
+                error!(event="TLS_CERT_ROTATE_FAIL", error=%format!("{:#}", e), "Yeni TLS materyali geçersiz, mevcut sertifikalar korunuyor");
+                Err(e)
+            }
+        }
+    }
+
+    /// Sertifika, anahtar ve CA dosyalarının dizinlerini izler.
+    pub fn spawn_watcher(&self) -> Result<()> {
+        let (tx, mut rx) = mpsc::channel::<()>(8);
+        let mut watcher: RecommendedWatcher = notify::recommended_watcher(move |res: notify::Result<notify::Event>| {
+            if let Ok(event) = res {
+                if event.kind.is_modify() || event.kind.is_create() || event.kind.is_remove() {
+                    let _ = tx.try_send(());
+                }
+            }
+        })?;
+
+        let dirs: HashSet<PathBuf> = [&self.config.cert_path, &self.config.key_path, &self.config.ca_path]
+            .into_iter()
+            .chain(self.config.crl_path.as_ref())
+            .map(|p| Path::new(p.as_str()).parent().filter(|d| !d.as_os_str().is_empty()).unwrap_or(Path::new(".")).to_path_buf())
+            .collect();
+        for dir in &dirs {
+            watcher.watch(dir, RecursiveMode::NonRecursive)?;
+        }
+        info!(event="TLS_WATCH_START", dirs=?dirs, "TLS sertifika dizinleri izleniyor");
+
+        let state = self.clone();
+        tokio::spawn(async move {
+            let _watcher = watcher;
+            while rx.recv().await.is_some() {
+                // cert-manager dosyaları art arda yazar; tümü yerine oturana kadar bekle.
+                tokio::time::sleep(WATCH_DEBOUNCE).await;
+                while rx.try_recv().is_ok() {}
+                let _ = state.reload().await;
+            }
+        });
+        Ok(())
+    }
+
+    /// `addr` üzerinde TCP dinler ve her bağlantı için o anki sunucu yapılandırmasıyla
+    /// TLS el sıkışması yapar. Tonic'e `serve_with_incoming` ile verilecek akışı döner.
+    /// İstemci sertifikası seri/parmak izi listelerine takılan bağlantılar kapatılır.
+    pub async fn incoming(&self, addr: SocketAddr, config: SharedConfig) -> Result<ReceiverStream<Result<TlsStream<TcpStream>, std::io::Error>>> {
+        let listener = TcpListener::bind(addr).await.context("gRPC portu dinlenemedi")?;
+        let (tx, rx) = mpsc::channel(128);
+        let server_config = self.server_config.clone();
+
+        tokio::spawn(async move {
+            loop {
+                let (tcp, peer) = match listener.accept().await {
+                    Ok(conn) => conn,
+                    Err(e) => {
+                        warn!(event="TCP_ACCEPT_FAIL", error=%e, "Bağlantı kabul edilemedi");
+                        tokio::time::sleep(Duration::from_millis(100)).await;
+                        continue;
+                    }
+                };
+                let _ = tcp.set_nodelay(true);
+                let acceptor = TlsAcceptor::from(server_config.load_full());
+                let tx = tx.clone();
+                let config = config.clone();
+                tokio::spawn(async move {
+                    match tokio::time::timeout(HANDSHAKE_TIMEOUT, acceptor.accept(tcp)).await {
+                        Ok(Ok(stream)) => {
+                            let peer_cert = stream.get_ref().1.peer_certificates()
+                                .and_then(|certs| certs.first())
+                                .and_then(|cert| PeerCert::parse(cert.as_ref()));
+                            if let Err(reason) = check_client_cert(&config.load(), peer_cert.as_ref()) {
+                                metrics::inc_counter("registrar_tls_handshake_failures_total", &[("reason", reason)]);
+                                warn!(
+                                    event = "TLS_CLIENT_CERT_REJECTED",
+                                    audit = true,
+                                    peer = %peer,
+                                    reason = reason,
+                                    peer.identities = ?peer_cert.as_ref().map(|c| &c.identities),
+                                    cert.serial = ?peer_cert.as_ref().map(|c| &c.serial),
+                                    cert.fingerprint = ?peer_cert.as_ref().map(|c| &c.fingerprint),
+                                    "İstemci sertifikası reddedildi, bağlantı kapatılıyor"
+                                );
+                                return;
+                            }
+                            let _ = tx.send(Ok(stream)).await;
+                        }
+                        Ok(Err(e)) => {
+                            metrics::inc_counter("registrar_tls_handshake_failures_total", &[("reason", "error")]);
+                            debug!(event="TLS_HANDSHAKE_FAIL", peer=%peer, error=%e, "TLS el sıkışması başarısız");
+                        }
+                        Err(_) => {
+                            metrics::inc_counter("registrar_tls_handshake_failures_total", &[("reason", "timeout")]);
+                            debug!(event="TLS_HANDSHAKE_TIMEOUT", peer=%peer, "TLS el sıkışması zaman aşımı");
+                        }
+                    }
+                });
+            }
+        });
+
+        Ok(ReceiverStream::new(rx))
+    }
+}
+
+fn record_expiry(material: &TlsMaterial) {
+    metrics::set_gauge("registrar_tls_cert_expiry_timestamp_seconds", &[("cert", "server")], material.not_after as f64);
+    let remaining_days = (material.not_after - chrono::Utc::now().timestamp()) / 86_400;
+    if remaining_days < 7 {
+        warn!(event="TLS_CERT_EXPIRING", remaining_days=remaining_days, "Sunucu sertifikasının süresi dolmak üzere");
+    }
+}
+
+/// Seri/parmak izi listelerini uygular ve istemci sertifikasının kalan süresini raporlar.
+fn check_client_cert(config: &AppConfig, cert: Option<&PeerCert>) -> Result<(), &'static str> {
+    let Some(cert) = cert else {
+        return if config.client_cert_allowlist.is_empty() { Ok(()) } else { Err("not_allowlisted") };
     };
 
-    Ok(ServerTlsConfig::new()
-        .identity(identity)
-        .client_ca_root(client_ca_cert))
-}
\ No newline at end of file
+    let listed = |list: &[String]| list.iter().any(|id| *id == cert.serial || *id == cert.fingerprint);
+    if listed(&config.client_cert_denylist) {
+        return Err("denylisted");
+    }
+    if !config.client_cert_allowlist.is_empty() && !listed(&config.client_cert_allowlist) {
+        return Err("not_allowlisted");
+    }
+
+    let identity = cert.primary_identity();
+    metrics::set_gauge("registrar_tls_client_cert_expiry_timestamp_seconds", &[("identity", identity)], cert.not_after as f64);
+    let remaining_days = (cert.not_after - chrono::Utc::now().timestamp()) / 86_400;
+    if remaining_days < config.client_cert_expiry_warn_days as i64 {
+        metrics::inc_counter("registrar_tls_client_cert_expiring_total", &[("identity", identity)]);
+        warn!(event="TLS_CLIENT_CERT_EXPIRING", identity=%identity, remaining_days=remaining_days, "İstemci sertifikasının süresi dolmak üzere");
+    }
+    Ok(())
+}